@@ -0,0 +1,97 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+/// Manifest file at the root of every bundle.
+pub const MANIFEST: &str = "template.json";
+/// Directory inside a bundle holding the files that `copy` steps read from.
+pub const FILES_DIR: &str = "files";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveKind {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveKind {
+    /// Detect an archive from its file name or URL path.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Lowercase hex SHA-256 of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Unpack an archive under `cache`, in a directory named after its content
+/// hash, and return the bundle root inside it.
+pub fn extract_archive(bytes: &[u8], kind: ArchiveKind, cache: &Path) -> anyhow::Result<PathBuf> {
+    let target = cache.join(sha256_hex(bytes));
+    if !target.join(".complete").exists() {
+        debug!("Extracting bundle into {}", target.display());
+        if target.exists() {
+            std::fs::remove_dir_all(&target)?;
+        }
+        std::fs::create_dir_all(&target)?;
+        match kind {
+            ArchiveKind::TarGz => {
+                let decoder = flate2::read::GzDecoder::new(Cursor::new(bytes));
+                tar::Archive::new(decoder)
+                    .unpack(&target)
+                    .context("Failed to extract tar.gz bundle")?;
+            }
+            ArchiveKind::Zip => extract_zip(bytes, &target)?,
+        }
+        std::fs::write(target.join(".complete"), "")?;
+    }
+    bundle_root(&target)
+}
+
+fn extract_zip(bytes: &[u8], target: &Path) -> anyhow::Result<()> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .context("Failed to read zip bundle")?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(name) = entry.enclosed_name() else {
+            anyhow::bail!("Zip bundle entry {} escapes the archive", entry.name()?);
+        };
+        let out = target.join(name);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&out)?;
+            continue;
+        }
+        if let Some(parent) = out.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::File::create(&out)?;
+        std::io::copy(&mut entry, &mut file)?;
+    }
+    Ok(())
+}
+
+/// Find the directory holding `template.json`: the directory itself, or its
+/// only subdirectory (archives often wrap everything in a top-level folder).
+pub fn bundle_root(dir: &Path) -> anyhow::Result<PathBuf> {
+    if dir.join(MANIFEST).is_file() {
+        return Ok(dir.to_path_buf());
+    }
+    let subdirs: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    match subdirs.as_slice() {
+        [only] if only.join(MANIFEST).is_file() => Ok(only.clone()),
+        _ => anyhow::bail!("No {} found in bundle {}", MANIFEST, dir.display()),
+    }
+}
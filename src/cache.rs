@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::Context;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use crate::bundle::sha256_hex;
+use crate::config::Config;
+
+/// What the cache knows about one downloaded URL. The body itself is stored
+/// once per content hash under `objects/`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    pub sha256: String,
+    pub size: u64,
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// Seconds since the Unix epoch of the last successful fetch or revalidation.
+    pub fetched_at: u64,
+}
+
+/// Content-addressed cache for templates, bundles and downloaded assets.
+///
+/// Online, cached responses are revalidated with `If-None-Match` /
+/// `If-Modified-Since`, and used as-is if the server cannot be reached or
+/// answers with a server error.
+/// Offline, the cache is the only source and a missing entry is an error.
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    root: PathBuf,
+    offline: bool,
+    http: reqwest::Client,
+}
+
+impl HttpCache {
+    pub fn new(root: impl Into<PathBuf>, offline: bool) -> Self {
+        HttpCache {
+            root: root.into(),
+            offline,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// The cache under [`Config::cache_dir`], offline if the config says so.
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.cache_dir().join("http"), config.offline)
+    }
+
+    /// The body of `url`, from the network or the cache.
+    pub async fn get(&self, url: &Url) -> anyhow::Result<Vec<u8>> {
+        let cached = self.entry(url)?;
+        if self.offline {
+            return match cached {
+                Some(entry) => self.read_object(&entry),
+                None => anyhow::bail!("{} is not in the cache and --offline is set", url),
+            };
+        }
+
+        let mut request = self.http.get(url.clone());
+        if let Some(entry) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => match cached {
+                Some(entry) => {
+                    warn!("Could not reach {} ({}); using the cached copy", url, err);
+                    return self.read_object(&entry);
+                }
+                None => return Err(err).with_context(|| format!("Failed to fetch {}", url)),
+            },
+        };
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(mut entry) = cached {
+                debug!("{} not modified; using the cached copy", url);
+                let body = self.read_object(&entry)?;
+                entry.fetched_at = now();
+                self.write_entry(&entry)?;
+                return Ok(body);
+            }
+        }
+
+        if response.status().is_server_error() {
+            if let Some(entry) = cached {
+                warn!("{} answered {}; using the cached copy", url, response.status());
+                return self.read_object(&entry);
+            }
+        }
+
+        let response = response
+            .error_for_status()
+            .with_context(|| format!("Failed to fetch {}", url))?;
+        let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = response.bytes().await?.to_vec();
+
+        let entry = CacheEntry {
+            url: url.to_string(),
+            sha256: sha256_hex(&body),
+            size: body.len() as u64,
+            etag,
+            last_modified,
+            fetched_at: now(),
+        };
+        let object = self.object_path(&entry.sha256);
+        if !object.exists() {
+            std::fs::create_dir_all(self.root.join("objects"))?;
+            std::fs::write(&object, &body)?;
+        }
+        self.write_entry(&entry)?;
+        Ok(body)
+    }
+
+    /// Every cached URL, sorted by URL.
+    pub fn entries(&self) -> anyhow::Result<Vec<CacheEntry>> {
+        let index = self.root.join("index");
+        if !index.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        for file in std::fs::read_dir(index)? {
+            let path = file?.path();
+            match read_entry(&path) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => debug!("Ignoring {}: {}", path.display(), err),
+            }
+        }
+        entries.sort_by(|a, b| a.url.cmp(&b.url));
+        Ok(entries)
+    }
+
+    fn entry(&self, url: &Url) -> anyhow::Result<Option<CacheEntry>> {
+        let path = self.entry_path(url.as_str());
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let entry = read_entry(&path)?;
+        Ok(self.object_path(&entry.sha256).is_file().then_some(entry))
+    }
+
+    fn write_entry(&self, entry: &CacheEntry) -> anyhow::Result<()> {
+        let path = self.entry_path(&entry.url);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(entry)?)?;
+        Ok(())
+    }
+
+    fn read_object(&self, entry: &CacheEntry) -> anyhow::Result<Vec<u8>> {
+        std::fs::read(self.object_path(&entry.sha256))
+            .with_context(|| format!("Failed to read the cached copy of {}", entry.url))
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.root.join("index").join(format!("{}.json", sha256_hex(url.as_bytes())))
+    }
+
+    fn object_path(&self, sha256: &str) -> PathBuf {
+        self.root.join("objects").join(sha256)
+    }
+}
+
+fn read_entry(path: &Path) -> anyhow::Result<CacheEntry> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Total size in bytes of the files below `dir`.
+pub fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else { return 0 };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            _ => entry.metadata().map(|m| m.len()).unwrap_or_default(),
+        })
+        .sum()
+}
@@ -1,11 +1,23 @@
-use std::string::ToString;
-use anyhow::Context;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use clap::{Parser, Subcommand};
-use reqwest::Url;
-use tracing::{info};
-use crate::model::Template;
-use crate::utils::init_logger;
-use crate::utils::print::{display_template_info};
+use tracing::{error, info, warn};
+use crate::cache::{dir_size, HttpCache};
+use crate::config::Config;
+use crate::loader::{load_template, load_template_with_sources, TemplateSource};
+use crate::model::answers::Answers;
+use crate::model::context::ExecutionContext;
+use crate::model::manifest::ProjectManifest;
+use crate::model::step::StepType;
+use crate::picker::{candidates, pick, record_recent, RECENT_FILE};
+use crate::registry::{RegistryClient, SearchQuery};
+use crate::trust::{authorize, side_effects, SideEffectPolicy};
+use crate::update::{update, NEW_SUFFIX};
+use crate::utils::{init_logger, scaf_home};
+use crate::utils::print::{
+    display_cache, display_plan, display_search_results, display_template_details, display_template_info,
+    print_section_footer, print_section_header,
+};
 
 const BANNER: &str = r#"
 ╭──────────────────────────────────────────╮
@@ -17,8 +29,6 @@ const BANNER: &str = r#"
 ╰──────────────────────────────────────────╯
 "#;
 
-const BASE_URL: &str = "https://scaf.pages.dev"; // std::env::var("BASE_URL").unwrap_or("https://scaf.pages.dev".to_string()).as_str();
-
 pub(crate) fn bootstrap_cli() {
     init_logger();
     println!("{}", BANNER);
@@ -28,7 +38,10 @@ pub(crate) fn bootstrap_cli() {
 #[derive(Parser)]
 #[command(name = "scaf")]
 #[command(about = "A CLI to scaffold projects from templates", long_about = None)]
-pub(crate) struct Cli {
+pub struct Cli {
+    /// Use only cached templates and downloads; fail if something is not cached
+    #[arg(long, global = true)]
+    offline: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -36,51 +49,267 @@ pub(crate) struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     New {
+        /// Template reference; pick one interactively when omitted
+        template: Option<String>,
+        #[arg(short, long)]
+        file: bool,
+        /// Set an argument value, skipping its prompt (repeatable)
+        #[arg(long = "arg", value_name = "KEY=VALUE")]
+        args: Vec<String>,
+        /// Read argument values from a JSON or YAML file
+        #[arg(long, value_name = "FILE")]
+        answers: Option<PathBuf>,
+        /// Never prompt; use defaults for anything not supplied
+        #[arg(short = 'y', long, visible_alias = "yes")]
+        defaults: bool,
+        /// Print the resolved steps instead of running them
+        #[arg(long)]
+        dry_run: bool,
+        /// Directory to scaffold into (default: `dest` from the config, or the current directory);
+        /// every step path must stay inside it
+        #[arg(short, long, value_name = "DIR")]
+        dest: Option<PathBuf>,
+        /// Render unresolved placeholders as empty instead of failing the step
+        #[arg(long)]
+        no_strict: bool,
+        /// Run command, dependencies, download and git steps without asking, even for untrusted templates
+        #[arg(long, conflicts_with = "no_commands")]
+        allow_commands: bool,
+        /// Skip command, dependencies, download and git steps
+        #[arg(long)]
+        no_commands: bool,
+    },
+    /// Re-apply the project's template, merging its changes into existing files
+    Update {
+        /// Template to update to; defaults to the one recorded in .scaf.json
+        template: Option<String>,
+        #[arg(short, long)]
+        file: bool,
+        /// Set an argument value, overriding the recorded one (repeatable)
+        #[arg(long = "arg", value_name = "KEY=VALUE")]
+        args: Vec<String>,
+        /// Read argument values from a JSON or YAML file
+        #[arg(long, value_name = "FILE")]
+        answers: Option<PathBuf>,
+        /// Never prompt; use defaults for anything not recorded or supplied
+        #[arg(short = 'y', long, visible_alias = "yes")]
+        defaults: bool,
+        /// Project directory to update (default: the current directory; `dest` from the config only applies to `new`)
+        #[arg(short, long, value_name = "DIR")]
+        dest: Option<PathBuf>,
+        /// Render unresolved placeholders as empty instead of failing the step
+        #[arg(long)]
+        no_strict: bool,
+        /// Run command, dependencies, download and git steps without asking, even for untrusted templates
+        #[arg(long, conflicts_with = "no_commands")]
+        allow_commands: bool,
+        /// Skip command, dependencies, download and git steps
+        #[arg(long)]
+        no_commands: bool,
+    },
+    /// Search the template registry
+    Search {
+        /// Text to look for in names and descriptions; lists everything when omitted
+        query: Option<String>,
+        /// Only templates with this tag (repeatable); filters the page of results
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Only templates for this language; filters the page of results
+        #[arg(long)]
+        language: Option<String>,
+        #[arg(long, default_value_t = 1)]
+        page: u32,
+    },
+    /// Show a registry template's details and arguments
+    Info {
+        /// Template id, as `id` or `username/id`
+        id: String,
+    },
+    /// Check a template for problems without running it
+    Validate {
         template: String,
         #[arg(short, long)]
         file: bool,
     },
+    /// Inspect or clear the template and download cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// List cached downloads, bundles and git checkouts
+    List,
+    /// Delete everything in the cache
+    Clean,
+}
+
+/// Answers from an `--answers` file and `--arg` pairs, falling back to `recorded` values.
+fn build_answers(file: Option<PathBuf>, pairs: &[String], defaults: bool,
+                 recorded: &BTreeMap<String, String>) -> anyhow::Result<Answers> {
+    let mut answers = match file {
+        Some(path) => Answers::from_file(&path, defaults)?,
+        None => Answers::new(defaults),
+    };
+    for (key, value) in recorded {
+        if answers.get(key).is_none() {
+            answers.insert(key, serde_json::Value::String(value.clone()));
+        }
+    }
+    for pair in pairs {
+        answers.insert_pair(pair)?;
+    }
+    Ok(answers)
 }
 
 impl Cli {
     pub(crate) async fn run(self) -> Result<(), anyhow::Error>  {
+        let mut config = Config::load()?;
+        config.offline |= self.offline;
         match self.command {
-            Commands::New { template, file } => {
-                info!("Loading template from {}", if file { "file" } else { "URL" });
-                let template_content = if file {
-                    std::fs::read_to_string(&template)
-                        .context("Failed to read template file")?
-                } else {
-                    let url: Url;
-                    if template.starts_with("http") {
-                        url = Url::parse(&template)?;
-                    } else if !template.contains("/") {
-                        url = Url::parse(&format!("{}/api/scaf/{}", BASE_URL, template))?;
-                    } else if template.split("/").count() == 2 {
-                        url = Url::parse(&format!("{}/api/{}", BASE_URL, template))?;
-                    } else {
-                        anyhow::bail!("Invalid template URL")
+            Commands::New {
+                template, file, args, answers, defaults, dry_run, dest, no_strict, allow_commands, no_commands,
+            } => {
+                let answers = build_answers(answers, &args, defaults, &Default::default())?;
+
+                let home = scaf_home();
+                let template = match template {
+                    Some(template) => template,
+                    None if answers.non_interactive => {
+                        anyhow::bail!("No template given; pass one to `scaf new` when running non-interactively")
                     }
-                    reqwest::get(&url.to_string())
-                        .await?
-                        .text()
-                        .await?
+                    None => pick(&candidates(&config, &home).await)?,
                 };
-
-                let temp: Template = serde_json::from_str(&template_content)?;
+                let source = TemplateSource::parse(&template, file, &config)?;
+                let (temp, sources) = load_template_with_sources(&source, &config).await?;
                 display_template_info(&temp);
 
-                let args_values = temp.collect_arguments().await?;
-                let (executed_steps, skipped_steps) = temp.execute(&args_values).await?;
+                let dest = dest.or_else(|| config.dest.clone()).unwrap_or_else(|| PathBuf::from("."));
+                let ctx = ExecutionContext::new(dest)
+                    .with_template(&temp)
+                    .with_cache(HttpCache::from_config(&config))
+                    .strict(!no_strict);
+                let args_values = temp.collect_arguments(&answers).await?;
+                if dry_run {
+                    display_plan(&temp.plan(&args_values, &ctx));
+                    return Ok(());
+                }
+                let policy = SideEffectPolicy::from_flags(allow_commands, no_commands);
+                let effects = side_effects(&temp, &args_values, &ctx);
+                let allowed = authorize(&effects, &sources, &config, policy, answers.non_interactive)?;
+                let ctx = ctx.side_effects(allowed);
+                let report = temp.execute(&args_values, &ctx).await?;
+                ProjectManifest::new(&temp, &source.recorded(), &args_values, &report, &ctx)?
+                    .save(&ctx.dest, &ctx.dest)?;
+                if let Err(err) = record_recent(&home.join(RECENT_FILE), &source.recorded(), &temp) {
+                    warn!("Could not record {} as recently used: {}", source, err);
+                }
 
                 println!("\n──────────────────────────────── Result ────────────────────────────");
                 println!(" ✨ Project scaffolded successfully!  ");
-                println!(" 📊 Executed: {}/{} steps             ", executed_steps, temp.steps.len());
-                println!(" ⏭️  Skipped: {} steps                ", skipped_steps);
+                println!(" 📊 Executed: {}/{} steps             ", report.executed.len(), temp.steps.len());
+                println!(" ⏭️  Skipped: {} steps                ", report.skipped.len());
                 println!("────────────────────────────────────────────────────────────────────\n");
             }
+            Commands::Update {
+                template, file, args, answers, defaults, dest, no_strict, allow_commands, no_commands,
+            } => {
+                let dest = dest.unwrap_or_else(|| PathBuf::from("."));
+                let previous = ProjectManifest::load(&dest)?;
+                let source = match template {
+                    Some(template) => TemplateSource::parse(&template, file, &config)?,
+                    None => TemplateSource::from_recorded(&previous.source, &config)?,
+                };
+                let answers = build_answers(answers, &args, defaults, &previous.answers)?;
+
+                let (temp, sources) = load_template_with_sources(&source, &config).await?;
+                display_template_info(&temp);
+                info!("Updating {} from {} to {}", previous.name, previous.version, temp.version);
+
+                let ctx = ExecutionContext::new(dest)
+                    .with_template(&temp)
+                    .with_cache(HttpCache::from_config(&config))
+                    .strict(!no_strict);
+                let args_values = temp.collect_arguments(&answers).await?;
+                // Commands and git steps that ran before are not run again.
+                let mut effects = side_effects(&temp, &args_values, &ctx);
+                effects.retain(|effect| {
+                    effect.step_type == StepType::Download || !previous.executed_steps.contains(&effect.step)
+                });
+                let policy = SideEffectPolicy::from_flags(allow_commands, no_commands);
+                let allowed = authorize(&effects, &sources, &config, policy, answers.non_interactive)?;
+                let ctx = ctx.side_effects(allowed);
+                let report = update(&temp, &source.recorded(), &previous, &args_values, &ctx).await?;
+
+                print_section_header("Update");
+                for (label, files) in [
+                    ("Added", &report.added),
+                    ("Updated", &report.updated),
+                    ("Merged", &report.merged),
+                    ("Removed", &report.removed),
+                    ("New steps", &report.steps),
+                ] {
+                    if !files.is_empty() {
+                        info!("{}: {}", label, files.join(", "));
+                    }
+                }
+                for conflict in &report.conflicts {
+                    let new = format!("{}{}", conflict, NEW_SUFFIX);
+                    if ctx.dest.join(&new).exists() {
+                        warn!("Conflict in {}; compare it with {} by hand", conflict, new);
+                    } else {
+                        warn!("Conflict in {}; resolve it by hand", conflict);
+                    }
+                }
+                print_section_footer();
+            }
+            Commands::Search { query, tag, language, page } => {
+                let client = RegistryClient::from_config(&config)?;
+                let results = client.search(&SearchQuery {
+                    text: query,
+                    tags: tag,
+                    language,
+                    page,
+                    limit: 0,
+                }).await?;
+                display_search_results(&results);
+            }
+            Commands::Info { id } => {
+                let id = config.expand(&id);
+                let client = RegistryClient::from_config(&config)?;
+                display_template_details(&client.get(id).await?);
+            }
+            Commands::Validate { template, file } => {
+                let temp = load_template(&TemplateSource::parse(&template, file, &config)?, &config).await?;
+                let issues = temp.validate();
+                print_section_header("Validation");
+                for issue in &issues {
+                    error!("{}", issue);
+                }
+                if !issues.is_empty() {
+                    print_section_footer();
+                    anyhow::bail!("Template {} has {} problem(s)", temp.name, issues.len());
+                }
+                info!("Template {} is valid", temp.name);
+                print_section_footer();
+            }
+            Commands::Cache { command: CacheCommand::List } => {
+                display_cache(&config.cache_dir(), &HttpCache::from_config(&config).entries()?);
+            }
+            Commands::Cache { command: CacheCommand::Clean } => {
+                let dir = config.cache_dir();
+                if dir.exists() {
+                    let size = dir_size(&dir);
+                    std::fs::remove_dir_all(&dir)?;
+                    info!("Removed {} ({} bytes)", dir.display(), size);
+                } else {
+                    info!("The cache at {} is already empty", dir.display());
+                }
+            }
         }
         Ok(())
     }
 
-}
\ No newline at end of file
+}
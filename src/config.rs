@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use serde::Deserialize;
+use tracing::{debug, warn};
+use crate::model::Template;
+use crate::utils::cache_dir;
+
+/// Registry used when no configuration overrides it.
+pub const DEFAULT_REGISTRY_URL: &str = "https://scaf.pages.dev";
+/// Per-project configuration file, looked up from the current directory upwards.
+pub const PROJECT_CONFIG: &str = ".scafrc";
+
+/// Settings read from TOML files and `SCAF_*` environment variables:
+///
+/// ```toml
+/// registry_url = "https://templates.example.com"
+/// dest = "projects"
+/// trusted = ["https://github.com/acme", "https://templates.example.com"]
+///
+/// [defaults]
+/// author = "Jane Doe"
+///
+/// [aliases]
+/// svc = "gh:acme/templates/service@v2"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Base URL of the template registry.
+    pub registry_url: Option<String>,
+    /// Directory `scaf new` scaffolds into when `--dest` is not given.
+    pub dest: Option<PathBuf>,
+    /// Argument defaults that replace the template's own, e.g. `author` or `email`.
+    pub defaults: BTreeMap<String, serde_json::Value>,
+    /// Short names for template references.
+    pub aliases: BTreeMap<String, String>,
+    /// Serve templates and downloads from the cache only.
+    pub offline: bool,
+    /// Root of the cache; only settable through `SCAF_CACHE_DIR`, see [`Config::cache_dir`].
+    #[serde(skip)]
+    pub cache_dir: Option<PathBuf>,
+    /// Origins (URL prefixes, git repositories, registries) whose templates may
+    /// run commands, installs, downloads and git steps without asking.
+    pub trusted: Vec<String>,
+}
+
+impl Config {
+    /// Load every layer: the system file, the user file, the nearest `.scafrc`
+    /// and finally the environment. Later layers win.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::load_from(&Self::paths(), std::env::vars())
+    }
+
+    /// Configuration files in the order they are applied.
+    pub fn paths() -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if cfg!(windows) {
+            if let Some(data) = std::env::var_os("ProgramData") {
+                paths.push(PathBuf::from(data).join("scaf").join("config.toml"));
+            }
+        } else {
+            paths.push(PathBuf::from("/etc/scaf/config.toml"));
+        }
+
+        let user_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".config")));
+        if let Some(dir) = user_dir {
+            paths.push(dir.join("scaf").join("config.toml"));
+        }
+
+        if let Ok(cwd) = std::env::current_dir() {
+            if let Some(project) = cwd.ancestors().map(|dir| dir.join(PROJECT_CONFIG)).find(|p| p.is_file()) {
+                paths.push(project);
+            }
+        }
+        paths
+    }
+
+    /// Merge the files that exist in `paths`, then apply `SCAF_REGISTRY_URL`,
+    /// `SCAF_DEST`, `SCAF_OFFLINE`, `SCAF_CACHE_DIR`, `SCAF_TRUSTED` (comma-separated), `SCAF_DEFAULT_<KEY>`
+    /// and `SCAF_ALIAS_<NAME>` from `env`.
+    pub fn load_from(paths: &[PathBuf], env: impl IntoIterator<Item = (String, String)>) -> anyhow::Result<Self> {
+        let mut config = Config::default();
+        for path in paths {
+            if path.is_file() {
+                debug!("Reading config {}", path.display());
+                let mut layer = Self::read(path)?;
+                if path.file_name().is_some_and(|name| name == PROJECT_CONFIG) {
+                    layer.drop_untrusted_settings(path);
+                }
+                config.merge(layer);
+            }
+        }
+
+        for (name, value) in env {
+            let Some(name) = name.strip_prefix("SCAF_") else { continue };
+            match name {
+                "REGISTRY_URL" => config.registry_url = Some(value),
+                "DEST" => config.dest = Some(PathBuf::from(value)),
+                "OFFLINE" => config.offline = matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"),
+                "CACHE_DIR" => config.cache_dir = Some(PathBuf::from(value)),
+                "TRUSTED" => config.trusted.extend(
+                    value.split(',').map(str::trim).filter(|origin| !origin.is_empty()).map(str::to_string),
+                ),
+                _ => {
+                    if let Some(key) = name.strip_prefix("DEFAULT_") {
+                        config.defaults.insert(key.to_lowercase(), serde_json::Value::String(value));
+                    } else if let Some(alias) = name.strip_prefix("ALIAS_") {
+                        config.aliases.insert(alias.to_lowercase(), value);
+                    }
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Failed to parse config {}", path.display()))
+    }
+
+    /// A `.scafrc` comes with whatever project is checked out, so it may not
+    /// pick the registry or trust template origins.
+    fn drop_untrusted_settings(&mut self, path: &Path) {
+        if self.registry_url.take().is_some() {
+            warn!("Ignoring `registry_url` in {}; set it in the user config or SCAF_REGISTRY_URL", path.display());
+        }
+        if !std::mem::take(&mut self.trusted).is_empty() {
+            warn!("Ignoring `trusted` in {}; set it in the user config or SCAF_TRUSTED", path.display());
+        }
+    }
+
+    fn merge(&mut self, other: Config) {
+        if other.registry_url.is_some() {
+            self.registry_url = other.registry_url;
+        }
+        if other.dest.is_some() {
+            self.dest = other.dest;
+        }
+        self.defaults.extend(other.defaults);
+        self.aliases.extend(other.aliases);
+        self.offline |= other.offline;
+        self.trusted.extend(other.trusted);
+    }
+
+    pub fn registry_url(&self) -> &str {
+        self.registry_url.as_deref().unwrap_or(DEFAULT_REGISTRY_URL).trim_end_matches('/')
+    }
+
+    /// Where templates, bundles, git checkouts and downloads are cached.
+    pub fn cache_dir(&self) -> PathBuf {
+        self.cache_dir.clone().unwrap_or_else(cache_dir)
+    }
+
+    /// The reference an alias stands for, or `reference` itself.
+    pub fn expand<'a>(&'a self, reference: &'a str) -> &'a str {
+        self.aliases.get(reference).map(String::as_str).unwrap_or(reference)
+    }
+
+    /// Use the configured defaults for the template's matching arguments.
+    pub fn apply_defaults(&self, template: &mut Template) -> anyhow::Result<()> {
+        for arg in &mut template.args {
+            let Some(value) = self.defaults.get(&arg.key) else { continue };
+            let value = arg.answer_to_string(value)?;
+            arg.default = arg.validate_value(&value)
+                .map_err(|err| anyhow::anyhow!("Invalid configured default for `{}`: {}", arg.key, err))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use std::path::{Component, Path, PathBuf};
+use tokio::process::Command;
+use tracing::debug;
+use crate::bundle::sha256_hex;
+use crate::version::{highest_match, is_requirement, parse_requirement};
+
+/// File in a checkout's `.git` directory recording which `url#ref` it holds.
+pub const CHECKOUT_MARKER: &str = "scaf-source";
+
+/// A template living in a git repository.
+///
+/// Written as `git+<url>[//<subdir>][#<ref>]`, e.g.
+/// `git+file:///srv/templates.git//rust-service#v1.2`, or for GitHub as
+/// `gh:<org>/<repo>[/<subdir>][@<ref>]`. A ref starting with a range operator
+/// (`^1.2`, `~1.2.3`, `>=1.0`) picks the highest matching version tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitSource {
+    pub url: String,
+    pub reference: Option<String>,
+    pub subdir: Option<String>,
+}
+
+impl GitSource {
+    pub fn parse(reference: &str) -> Option<anyhow::Result<Self>> {
+        if let Some(rest) = reference.strip_prefix("git+") {
+            return Some(Self::parse_git(rest));
+        }
+        reference.strip_prefix("gh:").map(Self::parse_github)
+    }
+
+    fn parse_git(rest: &str) -> anyhow::Result<Self> {
+        let (location, git_ref) = match rest.split_once('#') {
+            Some((location, git_ref)) => (location, Some(git_ref.to_string())),
+            None => (rest, None),
+        };
+        // The first `//` belongs to the scheme (`file:///`, `https://`)
+        let scheme_end = location.find("://").map(|i| i + 3).unwrap_or(0);
+        let (url, subdir) = match location[scheme_end..].find("//") {
+            Some(i) => (&location[..scheme_end + i], Some(location[scheme_end + i + 2..].to_string())),
+            None => (location, None),
+        };
+        if url.is_empty() {
+            anyhow::bail!("Invalid git template reference: git+{}", rest);
+        }
+        check_not_option("URL", url)?;
+        if let Some(git_ref) = &git_ref {
+            check_not_option("ref", git_ref)?;
+        }
+        GitSource {
+            url: url.to_string(),
+            reference: git_ref.filter(|r| !r.is_empty()),
+            subdir: None,
+        }.with_subdir(subdir.as_deref().unwrap_or(""))
+    }
+
+    fn parse_github(rest: &str) -> anyhow::Result<Self> {
+        let (path, git_ref) = match rest.split_once('@') {
+            Some((path, git_ref)) => (path, Some(git_ref.to_string())),
+            None => (rest, None),
+        };
+        let mut parts = path.splitn(3, '/');
+        let (Some(org), Some(repo)) = (parts.next(), parts.next()) else {
+            anyhow::bail!("Invalid GitHub template reference gh:{}, expected gh:org/repo[@ref]", rest);
+        };
+        if org.is_empty() || repo.is_empty() {
+            anyhow::bail!("Invalid GitHub template reference gh:{}, expected gh:org/repo[@ref]", rest);
+        }
+        if let Some(git_ref) = &git_ref {
+            check_not_option("ref", git_ref)?;
+        }
+        GitSource {
+            url: format!("https://github.com/{}/{}.git", org, repo.trim_end_matches(".git")),
+            reference: git_ref.filter(|r| !r.is_empty()),
+            subdir: None,
+        }.with_subdir(parts.next().unwrap_or(""))
+    }
+
+    /// The same repository and ref, pointing at `subdir` inside it.
+    pub fn with_subdir(mut self, subdir: &str) -> anyhow::Result<Self> {
+        let mut normalized = PathBuf::new();
+        for component in Path::new(subdir).components() {
+            match component {
+                Component::CurDir => {}
+                Component::Normal(part) => normalized.push(part),
+                Component::ParentDir if normalized.pop() => {}
+                _ => anyhow::bail!("Path {} escapes the repository {}", subdir, self.url),
+            }
+        }
+        self.subdir = (!normalized.as_os_str().is_empty())
+            .then(|| normalized.to_string_lossy().replace('\\', "/"));
+        Ok(self)
+    }
+
+    /// Fetch the requested ref into a checkout below `cache` and return the
+    /// template directory. Offline, the existing checkout is used if it was made
+    /// for the same ref.
+    pub async fn checkout(&self, cache: &Path, offline: bool) -> anyhow::Result<PathBuf> {
+        check_not_option("URL", &self.url)?;
+        if let Some(git_ref) = &self.reference {
+            check_not_option("ref", git_ref)?;
+        }
+        let dir = cache.join(sha256_hex(self.url.as_bytes()));
+        let marker = dir.join(".git").join(CHECKOUT_MARKER);
+        let wanted = format!("{}#{}", self.url, self.reference.as_deref().unwrap_or("HEAD"));
+        if offline {
+            if std::fs::read_to_string(&marker).ok().as_deref() != Some(wanted.as_str()) {
+                anyhow::bail!("{} is not in the cache and --offline is set", wanted);
+            }
+            return Ok(self.template_dir(dir));
+        }
+        if !dir.join(".git").exists() {
+            tokio::fs::create_dir_all(&dir).await?;
+            git(&dir, &["init", "--quiet"]).await?;
+            git(&dir, &["remote", "add", "--", "origin", &self.url]).await?;
+        }
+        let git_ref = match self.reference.as_deref() {
+            Some(reference) if is_requirement(reference) => self.resolve_tag(&dir, reference).await?,
+            Some(reference) => reference.to_string(),
+            None => "HEAD".to_string(),
+        };
+        debug!("Fetching {} at {}", self.url, git_ref);
+        git(&dir, &["fetch", "--quiet", "--depth", "1", "--force", "--", "origin", &git_ref]).await?;
+        git(&dir, &["checkout", "--quiet", "--force", "FETCH_HEAD"]).await?;
+        tokio::fs::write(&marker, &wanted).await?;
+
+        Ok(self.template_dir(dir))
+    }
+
+    fn template_dir(&self, checkout: PathBuf) -> PathBuf {
+        match &self.subdir {
+            Some(subdir) => checkout.join(subdir),
+            None => checkout,
+        }
+    }
+
+    /// The highest tag (`1.2.3` or `v1.2.3`) matching a semver range such as `^1.2`.
+    async fn resolve_tag(&self, dir: &Path, requirement: &str) -> anyhow::Result<String> {
+        let wanted = parse_requirement(requirement)?;
+        let listing = git(dir, &["ls-remote", "--tags", "--refs", "origin"]).await?;
+        let tags: Vec<&str> = listing
+            .lines()
+            .filter_map(|line| line.split_once("refs/tags/").map(|(_, tag)| tag))
+            .collect();
+        let Some(tag) = highest_match(&wanted, tags.iter().copied()) else {
+            anyhow::bail!("No tag of {} matches {} (tags: {})", self.url, requirement, tags.join(", "));
+        };
+        debug!("Resolved {}#{} to tag {}", self.url, requirement, tag);
+        Ok(format!("refs/tags/{}", tag))
+    }
+}
+
+impl std::fmt::Display for GitSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "git+{}", self.url)?;
+        if let Some(subdir) = &self.subdir {
+            write!(f, "//{}", subdir)?;
+        }
+        if let Some(git_ref) = &self.reference {
+            write!(f, "#{}", git_ref)?;
+        }
+        Ok(())
+    }
+}
+
+/// Refuse a URL or ref that git would read as an option, such as `--upload-pack=...`.
+fn check_not_option(what: &str, value: &str) -> anyhow::Result<()> {
+    if value.starts_with('-') || value.chars().any(char::is_control) {
+        anyhow::bail!("Invalid git {} `{}`: it must not start with `-` or contain control characters", what, value);
+    }
+    Ok(())
+}
+
+async fn git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .map_err(|err| anyhow::anyhow!("Failed to run git: {}", err))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
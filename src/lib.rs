@@ -1,7 +1,17 @@
+pub mod bundle;
+pub mod cache;
 #[allow(dead_code)]
 pub mod cli;
+pub mod config;
+pub mod git;
+pub mod loader;
 pub mod model;
+pub mod picker;
+pub mod registry;
+pub mod trust;
+pub mod update;
 pub mod utils;
+pub mod version;
 
 pub use cli::*;
 pub use model::*;
@@ -0,0 +1,279 @@
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use anyhow::Context;
+use reqwest::Url;
+use tracing::{debug, info};
+use crate::bundle::{bundle_root, extract_archive, ArchiveKind, FILES_DIR, MANIFEST};
+use crate::cache::HttpCache;
+use crate::config::Config;
+use crate::git::GitSource;
+use crate::model::{ExtendArg, Template};
+use crate::registry::RegistryClient;
+use crate::version::{check_min_scaf_version, parse_requirement, parse_version};
+
+/// Where a template is read from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateSource {
+    File(PathBuf),
+    Url(Url),
+    Git(GitSource),
+    /// A registry template pinned with `id@<version or range>`, resolved when fetched.
+    Registry {
+        registry: String,
+        id: String,
+        requirement: String,
+    },
+}
+
+impl fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateSource::File(path) => write!(f, "{}", path.display()),
+            TemplateSource::Url(url) => write!(f, "{}", url),
+            TemplateSource::Git(git) => write!(f, "{}", git),
+            TemplateSource::Registry { id, requirement, .. } => write!(f, "{}@{}", id, requirement),
+        }
+    }
+}
+
+impl TemplateSource {
+    /// Parse a template reference as given on the command line: a file path when
+    /// `file` is set, otherwise an alias from `config`, a git repository
+    /// (`git+<url>`, `gh:org/repo`), a URL or a registry id (`id` or `username/id`,
+    /// optionally pinned with `@1.2.0` or `@^1.2`). Aliases may also stand for an
+    /// absolute or `./`-relative path.
+    pub fn parse(reference: &str, file: bool, config: &Config) -> anyhow::Result<Self> {
+        if file {
+            return Ok(TemplateSource::File(PathBuf::from(reference)));
+        }
+        let reference = config.expand(reference);
+        if let Some(git) = GitSource::parse(reference) {
+            return Ok(TemplateSource::Git(git?));
+        }
+        if reference.starts_with("http") {
+            return Ok(TemplateSource::Url(Url::parse(reference)?));
+        }
+        if reference.starts_with('/') || reference.starts_with("./") || reference.starts_with("../") {
+            return Ok(TemplateSource::File(PathBuf::from(reference)));
+        }
+        let client = RegistryClient::from_config(config)?;
+        if let Some((id, requirement)) = reference.split_once('@') {
+            client.template_url(id)?;
+            parse_requirement(requirement)?;
+            return Ok(TemplateSource::Registry {
+                registry: config.registry_url().to_string(),
+                id: id.to_string(),
+                requirement: requirement.to_string(),
+            });
+        }
+        Ok(TemplateSource::Url(client.template_url(reference)?))
+    }
+
+    /// Parse a reference stored by [`TemplateSource::recorded`].
+    pub fn from_recorded(reference: &str, config: &Config) -> anyhow::Result<Self> {
+        TemplateSource::parse(reference, Path::new(reference).is_absolute(), config)
+    }
+
+    /// The reference to store in a project's `.scaf.json`. Local paths are made
+    /// absolute so the project can be updated from any directory.
+    pub fn recorded(&self) -> String {
+        match self {
+            TemplateSource::File(path) => path
+                .canonicalize()
+                .unwrap_or_else(|_| path.clone())
+                .display()
+                .to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Resolve a reference found inside this template (e.g. in `extends`).
+    ///
+    /// URLs and registry ids resolve as on the command line; anything that looks
+    /// like a path (`./`, `../`, `/` or a `.json` suffix) resolves relative to this source.
+    pub fn join(&self, reference: &str, config: &Config) -> anyhow::Result<Self> {
+        let is_path = reference.starts_with("./")
+            || reference.starts_with("../")
+            || reference.starts_with('/')
+            || reference.ends_with(".json");
+        if !is_path || reference.starts_with("http") {
+            return TemplateSource::parse(reference, false, config);
+        }
+        match self {
+            TemplateSource::File(path) if path.is_dir() => Ok(TemplateSource::File(path.join(reference))),
+            TemplateSource::File(path) => {
+                let base = path.parent().unwrap_or(Path::new(""));
+                Ok(TemplateSource::File(base.join(reference)))
+            }
+            TemplateSource::Url(url) => Ok(TemplateSource::Url(url.join(reference)?)),
+            TemplateSource::Git(git) => {
+                let subdir = git.subdir.as_deref().unwrap_or("");
+                let base = if subdir.ends_with(".json") || ArchiveKind::from_name(subdir).is_some() {
+                    Path::new(subdir).parent().unwrap_or(Path::new(""))
+                } else {
+                    Path::new(subdir)
+                };
+                let joined = base.join(reference);
+                Ok(TemplateSource::Git(git.clone().with_subdir(&joined.to_string_lossy())?))
+            }
+            TemplateSource::Registry { .. } => {
+                anyhow::bail!("{} cannot be resolved relative to registry template {}", reference, self)
+            }
+        }
+    }
+
+    /// Read the template JSON, unpacking bundles (a directory, `.tar.gz` or `.zip`
+    /// holding `template.json` and a `files/` tree) along the way. Anything
+    /// remote goes through the cache.
+    pub async fn fetch(&self, config: &Config) -> anyhow::Result<Fetched> {
+        let cache = HttpCache::from_config(config);
+        let bundles = config.cache_dir().join("bundles");
+        match self {
+            TemplateSource::Git(git) => {
+                let path = git.checkout(&config.cache_dir().join("git"), config.offline).await?;
+                Box::pin(TemplateSource::File(path).fetch(config)).await
+            }
+            TemplateSource::Registry { registry, id, .. } => {
+                // Whether the served version satisfies `requirement` is checked once it is parsed.
+                let url = RegistryClient::new(registry, cache)?.template_url(id)?;
+                Box::pin(TemplateSource::Url(url).fetch(config)).await
+            }
+            TemplateSource::File(path) if path.is_dir() => self.read_bundle(&bundle_root(path)?).await,
+            TemplateSource::File(path) => match ArchiveKind::from_name(&path.to_string_lossy()) {
+                Some(kind) => {
+                    let bytes = tokio::fs::read(path)
+                        .await
+                        .with_context(|| format!("Failed to read bundle {}", path.display()))?;
+                    self.read_bundle(&extract_archive(&bytes, kind, &bundles)?).await
+                }
+                None => Ok(Fetched {
+                    content: tokio::fs::read_to_string(path)
+                        .await
+                        .with_context(|| format!("Failed to read template file {}", path.display()))?,
+                    files: None,
+                }),
+            },
+            TemplateSource::Url(url) => match ArchiveKind::from_name(url.path()) {
+                Some(kind) => self.read_bundle(&extract_archive(&cache.get(url).await?, kind, &bundles)?).await,
+                None => Ok(Fetched {
+                    content: String::from_utf8(cache.get(url).await?)
+                        .with_context(|| format!("Template {} is not valid UTF-8", url))?,
+                    files: None,
+                }),
+            },
+        }
+    }
+
+    /// The manifest and `files/` directory of the bundle unpacked at `root`.
+    async fn read_bundle(&self, root: &Path) -> anyhow::Result<Fetched> {
+        let content = tokio::fs::read_to_string(root.join(MANIFEST))
+            .await
+            .with_context(|| format!("Failed to read {} in bundle {}", MANIFEST, self))?;
+        let files = root.join(FILES_DIR);
+        Ok(Fetched {
+            content,
+            files: files.is_dir().then_some(files),
+        })
+    }
+}
+
+/// A fetched template: its JSON and, for bundles, the `files/` directory.
+#[derive(Debug)]
+pub struct Fetched {
+    pub content: String,
+    pub files: Option<PathBuf>,
+}
+
+/// Load a template and resolve everything it `extends`.
+pub async fn load_template(source: &TemplateSource, config: &Config) -> anyhow::Result<Template> {
+    Ok(load_template_with_sources(source, config).await?.0)
+}
+
+/// Like [`load_template`], also returning every source the template was built
+/// from: `source` itself first, then each template it `extends`, recursively.
+pub async fn load_template_with_sources(source: &TemplateSource, config: &Config)
+    -> anyhow::Result<(Template, Vec<TemplateSource>)> {
+    info!("Loading template from {}", source);
+    let mut sources = Vec::new();
+    let template = resolve(source, config, &mut Vec::new(), &mut sources).await?;
+    Ok((template, sources))
+}
+
+fn resolve<'a>(
+    source: &'a TemplateSource,
+    config: &'a Config,
+    chain: &'a mut Vec<String>,
+    sources: &'a mut Vec<TemplateSource>,
+) -> Pin<Box<dyn Future<Output = anyhow::Result<Template>> + 'a>> {
+    Box::pin(async move {
+        let key = source.recorded();
+        if chain.contains(&key) {
+            chain.push(key);
+            anyhow::bail!("Template extends cycle: {}", chain.join(" -> "));
+        }
+
+        let fetched = source.fetch(config).await?;
+        sources.push(source.clone());
+        // The registry answers `null` for templates it does not know.
+        if fetched.content.trim() == "null" {
+            anyhow::bail!("Template {} was not found", source);
+        }
+        let mut template: Template = serde_json::from_str(&fetched.content)
+            .with_context(|| format!("Failed to parse template {}", source))?;
+        template.files.extend(fetched.files);
+        let version = parse_version(&template.version)
+            .with_context(|| format!("Template {} has an invalid version", template.name))?;
+        if let TemplateSource::Registry { id, requirement, .. } = source {
+            if !parse_requirement(requirement)?.matches(&version) {
+                anyhow::bail!(
+                    "No version of {} matches {}: the registry does not list versions and serves {}",
+                    id, requirement, template.version
+                );
+            }
+        }
+        if let Some(min) = &template.min_scaf_version {
+            check_min_scaf_version(&template.name, min)?;
+        }
+        // Before the `extends` values of a child template, which win over the config.
+        config.apply_defaults(&mut template)?;
+
+        chain.push(key);
+        let mut parents = Vec::new();
+        for extend in &template.extends {
+            debug!("Resolving {} extends {}", template.name, extend.template_id);
+            let parent_source = source.join(&extend.template_id, config)?;
+            let mut parent = resolve(&parent_source, config, chain, sources).await?;
+            if !parse_requirement(&extend.version)?.matches(&parse_version(&parent.version)?) {
+                anyhow::bail!(
+                    "Template {} extends {}@{} but found version {}",
+                    template.name, extend.template_id, extend.version, parent.version
+                );
+            }
+            prefill(&mut parent, &extend.args)
+                .with_context(|| format!("Invalid args for extended template {}", extend.template_id))?;
+            parents.push(parent);
+        }
+        chain.pop();
+
+        for parent in parents.into_iter().rev() {
+            template.inherit(parent);
+        }
+        Ok(template)
+    })
+}
+
+/// Use the `extends` arg values as the parent's defaults.
+fn prefill(parent: &mut Template, values: &[ExtendArg]) -> anyhow::Result<()> {
+    for value in values {
+        let arg = parent.args
+            .iter_mut()
+            .find(|arg| arg.key == value.name)
+            .with_context(|| format!("{} has no argument named `{}`", parent.name, value.name))?;
+        arg.validate_value(&value.value)
+            .map_err(|err| anyhow::anyhow!("Invalid value for `{}`: {}", value.name, err))?;
+        arg.default = value.value.clone();
+    }
+    Ok(())
+}
@@ -1,6 +1,16 @@
 mod model;
+mod bundle;
+mod cache;
 mod cli;
+mod config;
+mod git;
+mod loader;
+mod picker;
+mod registry;
+mod trust;
+mod update;
 mod utils;
+mod version;
 
 use crate::cli::{bootstrap_cli, Cli};
 use anyhow::{Result};
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::Context;
+use serde_json::Value;
+
+/// Argument values supplied up front instead of through prompts,
+/// gathered from `--arg key=value` flags and `--answers` files.
+#[derive(Debug, Default)]
+pub struct Answers {
+    values: HashMap<String, Value>,
+    /// Never prompt: fall back to defaults and fail on missing required values.
+    pub non_interactive: bool,
+}
+
+impl Answers {
+    pub fn new(non_interactive: bool) -> Self {
+        Answers {
+            values: HashMap::new(),
+            non_interactive,
+        }
+    }
+
+    /// Load answers from a JSON or YAML file (picked by extension) holding a flat object.
+    pub fn from_file(path: &Path, non_interactive: bool) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read answers file {}", path.display()))?;
+        let is_yaml = matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("yaml") | Some("yml")
+        );
+        let parsed: Value = if is_yaml {
+            serde_yaml::from_str(&content)
+                .with_context(|| format!("Invalid YAML in answers file {}", path.display()))?
+        } else {
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid JSON in answers file {}", path.display()))?
+        };
+
+        let Value::Object(map) = parsed else {
+            anyhow::bail!("Answers file {} must contain an object of key/value pairs", path.display());
+        };
+        Ok(Answers {
+            values: map.into_iter().collect(),
+            non_interactive,
+        })
+    }
+
+    /// Parse a `key=value` pair as given to `--arg`, overriding any earlier value.
+    pub fn insert_pair(&mut self, pair: &str) -> anyhow::Result<()> {
+        let (key, value) = pair
+            .split_once('=')
+            .with_context(|| format!("Invalid --arg `{}`, expected key=value", pair))?;
+        let key = key.trim();
+        if key.is_empty() {
+            anyhow::bail!("Invalid --arg `{}`, key cannot be empty", pair);
+        }
+        self.insert(key, Value::String(value.to_string()));
+        Ok(())
+    }
+
+    pub fn insert(&mut self, key: &str, value: Value) {
+        self.values.insert(key.to_string(), value);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.values.keys()
+    }
+}
@@ -1,7 +1,9 @@
-use dialoguer::{Input, MultiSelect, Select};
+use dialoguer::{Input, MultiSelect, Password, Select};
 use dialoguer::theme::ColorfulTheme;
 use serde::{Deserialize, Serialize};
 use tracing::{error, warn};
+use super::answers::Answers;
+use super::condition::Conditions;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TemplateArg {
@@ -26,8 +28,27 @@ pub struct TemplateArg {
     pub multiple: bool,
     #[serde(default)]
     pub delimiter: Option<String>,
+    /// Hide the input when prompting and leave the value out of `.scaf.json`.
+    #[serde(default)]
+    pub secret: bool,
+    /// Ask only when these hold for the values collected so far; otherwise
+    /// the arg takes its default, or no value if the default is empty.
+    #[serde(default)]
+    pub conditions: Option<Conditions>,
 }
 
+/// A section of the prompts, declared in the template's `groups`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArgGroup {
+    /// What args put in their `group` to belong here.
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Ask the group's args only when these hold for the values collected before it.
+    #[serde(default)]
+    pub conditions: Option<Conditions>,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "lowercase")]
@@ -49,6 +70,14 @@ pub struct ArgValue {
 }
 
 impl TemplateArg {
+    /// Keys of the args that must be collected first: `depends_on` and the
+    /// fields tested by `conditions`.
+    pub fn dependencies(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.depends_on.iter().flatten().map(String::as_str).collect();
+        keys.extend(self.conditions.iter().flat_map(Conditions::fields));
+        keys
+    }
+
     pub(crate) async fn collect_args(&self) -> anyhow::Result<String> {
         let theme = ColorfulTheme::default();
         if let Some(details) = self.get_details() {
@@ -72,7 +101,7 @@ impl TemplateArg {
                         .collect();
 
                     let selections = MultiSelect::with_theme(&theme)
-                        .with_prompt(&self.get_prompt())
+                        .with_prompt(self.get_prompt())
                         .items(&items)
                         .defaults(&defaults)
                         .interact()?;
@@ -94,16 +123,24 @@ impl TemplateArg {
                         .unwrap_or(0);
 
                     let selection = Select::with_theme(&theme)
-                        .with_prompt(&self.get_prompt())
+                        .with_prompt(self.get_prompt())
                         .items(&items)
                         .default(default_idx)
                         .interact()?;
 
                     value = values[selection].value.clone();
                 }
+            } else if self.secret {
+                value = Password::with_theme(&theme)
+                    .with_prompt(self.get_prompt())
+                    .allow_empty_password(!self.default.is_empty() || !self.required)
+                    .interact()?;
+                if value.is_empty() {
+                    value = self.default.clone();
+                }
             } else {
                 value = Input::with_theme(&theme)
-                    .with_prompt(&self.get_prompt())
+                    .with_prompt(self.get_prompt())
                     .default(self.default.clone())
                     .interact_text()?;
             }
@@ -122,6 +159,51 @@ impl TemplateArg {
 
         Ok(value)
     }
+    /// Resolve the value for this argument without prompting, if possible.
+    ///
+    /// Supplied answers win over defaults. `Ok(None)` means the value still has
+    /// to be asked for interactively.
+    pub fn resolve_answer(&self, answers: &Answers) -> anyhow::Result<Option<String>> {
+        if let Some(answer) = answers.get(&self.key) {
+            let value = self.answer_to_string(answer)?;
+            return self.validate_value(&value)
+                .map(Some)
+                .map_err(|err| anyhow::anyhow!("Invalid value for `{}`: {}", self.key, err));
+        }
+        if !answers.non_interactive {
+            return Ok(None);
+        }
+        if self.default.is_empty() {
+            if self.required {
+                anyhow::bail!(
+                    "Missing required argument `{}` ({}); pass it with --arg {}=<value> or an --answers file",
+                    self.key, self.name, self.key
+                );
+            }
+            return Ok(Some(String::new()));
+        }
+        self.validate_value(&self.default)
+            .map(Some)
+            .map_err(|err| anyhow::anyhow!("Invalid default for `{}`: {}", self.key, err))
+    }
+
+    pub(crate) fn answer_to_string(&self, answer: &serde_json::Value) -> anyhow::Result<String> {
+        use serde_json::Value;
+        match answer {
+            Value::String(s) => Ok(s.clone()),
+            Value::Number(n) => Ok(n.to_string()),
+            Value::Bool(b) => Ok(b.to_string()),
+            Value::Array(items) if self.multiple => {
+                let items = items
+                    .iter()
+                    .map(|item| self.answer_to_string(item))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok(items.join(self.delimiter.as_deref().unwrap_or(",")))
+            },
+            _ => anyhow::bail!("Unsupported value for `{}`: {}", self.key, answer),
+        }
+    }
+
     pub fn validate_value(&self, value: &str) -> Result<String, String> {
         if self.multiple {
             let delimiter = self.delimiter.as_deref().unwrap_or(",");
@@ -158,20 +240,14 @@ impl TemplateArg {
                     }
                 }
             },
-            ArgType::Email => {
-                if !value.contains('@') {
-                    return Err("Invalid email address".to_string());
-                }
+            ArgType::Email if !value.contains('@') => {
+                return Err("Invalid email address".to_string());
             },
-            ArgType::Url => {
-                if !value.starts_with("http://") && !value.starts_with("https://") {
-                    return Err("Invalid URL".to_string());
-                }
+            ArgType::Url if !value.starts_with("http://") && !value.starts_with("https://") => {
+                return Err("Invalid URL".to_string());
             },
-            ArgType::Path => {
-                if value.contains("..") {
-                    return Err("Path cannot contain ..".to_string());
-                }
+            ArgType::Path if value.contains("..") => {
+                return Err("Path cannot contain ..".to_string());
             },
             _ => {},
         }
@@ -0,0 +1,446 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::version::{parse_requirement, parse_version};
+use super::args::TemplateArg;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogicalOperator {
+    And,
+    Or,
+}
+
+/// A list of conditions joined by `and` or `or`.
+///
+/// In JSON this is either an object, `{"operator": "or", "conditions": [...]}`,
+/// or a compact expression such as `"database == 'postgres' && 'docker' in features"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ConditionsRepr")]
+pub struct Conditions {
+    #[serde(default = "default_operator")]
+    pub operator: LogicalOperator,
+    pub conditions: Vec<ConditionExpr>,
+}
+
+fn default_operator() -> LogicalOperator {
+    LogicalOperator::And
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConditionsRepr {
+    Expression(String),
+    List {
+        #[serde(default = "default_operator")]
+        operator: LogicalOperator,
+        conditions: Vec<ConditionExpr>,
+    },
+}
+
+impl TryFrom<ConditionsRepr> for Conditions {
+    type Error = String;
+
+    fn try_from(repr: ConditionsRepr) -> Result<Self, Self::Error> {
+        match repr {
+            ConditionsRepr::Expression(text) => text.parse(),
+            ConditionsRepr::List { operator, conditions } => Ok(Conditions { operator, conditions }),
+        }
+    }
+}
+
+/// One entry of a [`Conditions`] list: a comparison, a negation or a nested list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConditionExpr {
+    Not { not: Box<ConditionExpr> },
+    Compare(Condition),
+    Group(Conditions),
+}
+
+impl From<Condition> for ConditionExpr {
+    fn from(condition: Condition) -> Self {
+        ConditionExpr::Compare(condition)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Condition {
+    pub field: String,
+    pub operator: ConditionOperator,
+    /// Unused by `exists` and `empty`.
+    #[serde(default)]
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConditionOperator {
+    Equals,
+    #[serde(alias = "notEquals")]
+    NotEquals,
+    Contains,
+    #[serde(alias = "startsWith")]
+    StartsWith,
+    #[serde(alias = "endsWith")]
+    EndsWith,
+    In,
+    #[serde(alias = "notIn")]
+    NotIn,
+    /// Every item of the list in `value` is among the field's items.
+    #[serde(alias = "containsAll")]
+    ContainsAll,
+    /// At least one item of the list in `value` is among the field's items.
+    #[serde(alias = "containsAny")]
+    ContainsAny,
+    /// Numeric comparisons; values that are both semver versions compare as such.
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// The field matches the regex in `value`.
+    Matches,
+    /// The field is a version within the semver requirement in `value`.
+    Satisfies,
+    /// The field has a value.
+    Exists,
+    /// The field has no value or an empty one.
+    Empty,
+}
+
+/// How each argument's value splits into items for `in`, `contains` and friends.
+///
+/// A multi-valued argument splits on its delimiter and a single-valued one is
+/// a single item. Values of keys that are not arguments split on `,`.
+#[derive(Debug, Clone, Default)]
+pub struct ArgDelimiters {
+    delimiters: HashMap<String, Option<String>>,
+}
+
+impl ArgDelimiters {
+    pub fn for_args(args: &[TemplateArg]) -> Self {
+        let delimiters = args
+            .iter()
+            .map(|arg| {
+                let delimiter = arg.multiple.then(|| arg.delimiter.clone().unwrap_or_else(|| ",".to_string()));
+                (arg.key.clone(), delimiter)
+            })
+            .collect();
+        ArgDelimiters { delimiters }
+    }
+
+    fn is_multiple(&self, key: &str) -> bool {
+        !matches!(self.delimiters.get(key), Some(None))
+    }
+
+    /// The delimiter lists are written with for `key`.
+    fn delimiter(&self, key: &str) -> &str {
+        self.delimiters.get(key).and_then(Option::as_deref).unwrap_or(",")
+    }
+
+    /// The items of `key`'s value.
+    fn items<'a>(&self, key: &str, value: &'a str) -> Vec<&'a str> {
+        if self.is_multiple(key) {
+            split(value, self.delimiter(key))
+        } else {
+            vec![value.trim()]
+        }
+    }
+}
+
+fn split<'a>(value: &'a str, delimiter: &str) -> Vec<&'a str> {
+    value.split(delimiter).map(str::trim).filter(|item| !item.is_empty()).collect()
+}
+
+impl Conditions {
+    pub fn evaluate(&self, args: &HashMap<String, String>, delimiters: &ArgDelimiters) -> bool {
+        match self.operator {
+            LogicalOperator::And => self.conditions.iter().all(|c| c.evaluate(args, delimiters)),
+            LogicalOperator::Or => self.conditions.iter().any(|c| c.evaluate(args, delimiters)),
+        }
+    }
+
+    /// Every comparison, however deeply nested.
+    pub fn leaves(&self) -> Vec<&Condition> {
+        let mut leaves = Vec::new();
+        for expr in &self.conditions {
+            expr.collect_leaves(&mut leaves);
+        }
+        leaves
+    }
+
+    /// The argument keys these conditions test.
+    pub fn fields(&self) -> Vec<&str> {
+        self.leaves().into_iter().map(|c| c.field.as_str()).collect()
+    }
+}
+
+impl ConditionExpr {
+    pub fn evaluate(&self, args: &HashMap<String, String>, delimiters: &ArgDelimiters) -> bool {
+        match self {
+            ConditionExpr::Not { not } => !not.evaluate(args, delimiters),
+            ConditionExpr::Compare(condition) => condition.evaluate(args, delimiters),
+            ConditionExpr::Group(conditions) => conditions.evaluate(args, delimiters),
+        }
+    }
+
+    fn collect_leaves<'a>(&'a self, leaves: &mut Vec<&'a Condition>) {
+        match self {
+            ConditionExpr::Not { not } => not.collect_leaves(leaves),
+            ConditionExpr::Compare(condition) => leaves.push(condition),
+            ConditionExpr::Group(conditions) => leaves.extend(conditions.leaves()),
+        }
+    }
+}
+
+impl Condition {
+    pub fn evaluate(&self, args: &HashMap<String, String>, delimiters: &ArgDelimiters) -> bool {
+        let field_value = match args.get(&self.field) {
+            Some(value) => value,
+            None => return self.operator == ConditionOperator::Empty,
+        };
+        let items = || delimiters.items(&self.field, field_value);
+        let wanted = || split(&self.value, delimiters.delimiter(&self.field));
+
+        match self.operator {
+            ConditionOperator::Equals => field_value == &self.value,
+            ConditionOperator::NotEquals => field_value != &self.value,
+            ConditionOperator::Contains if delimiters.is_multiple(&self.field) => items().contains(&self.value.trim()),
+            ConditionOperator::Contains => field_value.contains(&self.value),
+            ConditionOperator::StartsWith => field_value.starts_with(&self.value),
+            ConditionOperator::EndsWith => field_value.ends_with(&self.value),
+            ConditionOperator::In => items().contains(&self.value.trim()),
+            ConditionOperator::NotIn => !items().contains(&self.value.trim()),
+            ConditionOperator::ContainsAll => {
+                let items = items();
+                wanted().iter().all(|item| items.contains(item))
+            }
+            ConditionOperator::ContainsAny => {
+                let items = items();
+                wanted().iter().any(|item| items.contains(item))
+            }
+            ConditionOperator::Gt => compare(field_value, &self.value).is_some_and(|o| o.is_gt()),
+            ConditionOperator::Gte => compare(field_value, &self.value).is_some_and(|o| o.is_ge()),
+            ConditionOperator::Lt => compare(field_value, &self.value).is_some_and(|o| o.is_lt()),
+            ConditionOperator::Lte => compare(field_value, &self.value).is_some_and(|o| o.is_le()),
+            ConditionOperator::Matches => regex::Regex::new(&self.value)
+                .is_ok_and(|re| re.is_match(field_value)),
+            ConditionOperator::Satisfies => match (parse_version(field_value), parse_requirement(&self.value)) {
+                (Ok(version), Ok(requirement)) => requirement.matches(&version),
+                _ => false,
+            },
+            ConditionOperator::Exists => true,
+            ConditionOperator::Empty => items().iter().all(|item| item.is_empty()),
+        }
+    }
+}
+
+/// Order two values as numbers, or failing that as semver versions.
+fn compare(left: &str, right: &str) -> Option<std::cmp::Ordering> {
+    if let (Ok(left), Ok(right)) = (left.trim().parse::<f64>(), right.trim().parse::<f64>()) {
+        return left.partial_cmp(&right);
+    }
+    match (parse_version(left), parse_version(right)) {
+        (Ok(left), Ok(right)) => Some(left.cmp(&right)),
+        _ => None,
+    }
+}
+
+impl std::str::FromStr for Conditions {
+    type Err = String;
+
+    /// Parse the compact form:
+    ///
+    /// - `field == 'value'`, `!=`, `>`, `>=`, `<`, `<=`, and the words `contains`,
+    ///   `containsAll`, `containsAny`, `startsWith`, `endsWith`, `matches` and
+    ///   `satisfies` between a field and a value,
+    /// - `'value' in field` and `'value' not in field` for multi-value fields,
+    /// - `exists(field)`, `empty(field)`, and a bare `field` for `field == 'true'`,
+    /// - `&&`/`and`, `||`/`or`, `!`/`not` and parentheses.
+    ///
+    /// Values are quoted with `'` or `"`, or written as plain numbers and versions.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens: &tokens, position: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("Unexpected {} in condition `{}`", token, text));
+        }
+        Ok(match expr {
+            ConditionExpr::Group(conditions) => conditions,
+            expr => Conditions { operator: LogicalOperator::And, conditions: vec![expr] },
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Literal(String),
+    Symbol(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Literal(value) => write!(f, "'{}'", value),
+            Token::Symbol(symbol) => write!(f, "`{}`", symbol),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 11] = ["&&", "||", "==", "!=", ">=", "<=", ">", "<", "!", "(", ")"];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c == '\'' || c == '"' {
+            let Some(end) = rest[1..].find(c) else {
+                return Err(format!("Unterminated string in condition `{}`", text));
+            };
+            tokens.push(Token::Literal(rest[1..end + 1].to_string()));
+            rest = &rest[end + 2..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else if c.is_alphanumeric() || "_-.".contains(c) {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || "_-.".contains(c)))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                tokens.push(Token::Literal(word.to_string()));
+            } else {
+                tokens.push(Token::Word(word.to_string()));
+            }
+            rest = &rest[end..];
+        } else {
+            return Err(format!("Unexpected `{}` in condition `{}`", c, text));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self.peek().cloned().ok_or("Condition ends unexpectedly")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Consume the next token if it is `symbol` or the keyword `word`.
+    fn eat(&mut self, symbol: &str, word: &str) -> bool {
+        let matched = match self.peek() {
+            Some(Token::Symbol(s)) => *s == symbol,
+            Some(Token::Word(w)) => w == word,
+            _ => false,
+        };
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    fn or(&mut self) -> Result<ConditionExpr, String> {
+        self.list(LogicalOperator::Or, "||", "or", Self::and)
+    }
+
+    fn and(&mut self) -> Result<ConditionExpr, String> {
+        self.list(LogicalOperator::And, "&&", "and", Self::unary)
+    }
+
+    fn list(&mut self, operator: LogicalOperator, symbol: &str, word: &str,
+            operand: fn(&mut Self) -> Result<ConditionExpr, String>) -> Result<ConditionExpr, String> {
+        let mut conditions = vec![operand(self)?];
+        while self.eat(symbol, word) {
+            conditions.push(operand(self)?);
+        }
+        Ok(match conditions.len() {
+            1 => conditions.remove(0),
+            _ => ConditionExpr::Group(Conditions { operator, conditions }),
+        })
+    }
+
+    fn unary(&mut self) -> Result<ConditionExpr, String> {
+        if self.eat("!", "not") {
+            return Ok(ConditionExpr::Not { not: Box::new(self.unary()?) });
+        }
+        if self.eat("(", "") {
+            let expr = self.or()?;
+            if !self.eat(")", "") {
+                return Err("Missing `)` in condition".to_string());
+            }
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<ConditionExpr, String> {
+        let compare = |field: String, operator: ConditionOperator, value: String| -> Result<ConditionExpr, String> {
+            Ok(Condition { field, operator, value }.into())
+        };
+        match self.next()? {
+            Token::Literal(value) => {
+                let operator = if self.eat("", "in") {
+                    ConditionOperator::In
+                } else if self.eat("", "not") && self.eat("", "in") {
+                    ConditionOperator::NotIn
+                } else {
+                    return Err(format!("Expected `in` or `not in` after '{}'", value));
+                };
+                match self.next()? {
+                    Token::Word(field) => compare(field, operator, value),
+                    token => Err(format!("Expected a field name, found {}", token)),
+                }
+            }
+            Token::Word(function) if matches!(function.as_str(), "exists" | "empty") && self.eat("(", "") => {
+                let Token::Word(field) = self.next()? else {
+                    return Err(format!("Expected a field name in {}(...)", function));
+                };
+                if !self.eat(")", "") {
+                    return Err(format!("Missing `)` after {}({}", function, field));
+                }
+                let operator = if function == "exists" { ConditionOperator::Exists } else { ConditionOperator::Empty };
+                compare(field, operator, String::new())
+            }
+            Token::Word(field) => {
+                let operator = match self.peek() {
+                    Some(Token::Symbol("==")) => ConditionOperator::Equals,
+                    Some(Token::Symbol("!=")) => ConditionOperator::NotEquals,
+                    Some(Token::Symbol(">")) => ConditionOperator::Gt,
+                    Some(Token::Symbol(">=")) => ConditionOperator::Gte,
+                    Some(Token::Symbol("<")) => ConditionOperator::Lt,
+                    Some(Token::Symbol("<=")) => ConditionOperator::Lte,
+                    Some(Token::Word(word)) => match word.as_str() {
+                        "contains" => ConditionOperator::Contains,
+                        "containsAll" => ConditionOperator::ContainsAll,
+                        "containsAny" => ConditionOperator::ContainsAny,
+                        "startsWith" => ConditionOperator::StartsWith,
+                        "endsWith" => ConditionOperator::EndsWith,
+                        "matches" => ConditionOperator::Matches,
+                        "satisfies" => ConditionOperator::Satisfies,
+                        _ => return compare(field, ConditionOperator::Equals, "true".to_string()),
+                    },
+                    _ => return compare(field, ConditionOperator::Equals, "true".to_string()),
+                };
+                self.position += 1;
+                match self.next()? {
+                    Token::Literal(value) => compare(field, operator, value),
+                    token => Err(format!("Expected a quoted value after `{}`, found {}", field, token)),
+                }
+            }
+            token => Err(format!("Unexpected {} in condition", token)),
+        }
+    }
+}
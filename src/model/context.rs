@@ -0,0 +1,129 @@
+use std::path::{Component, Path, PathBuf};
+use indicatif::ProgressBar;
+use crate::cache::HttpCache;
+use crate::utils::cache_dir;
+use crate::utils::render::Renderer;
+use super::condition::ArgDelimiters;
+use super::Template;
+
+/// Settings shared by every step of a single scaffold run.
+#[derive(Debug, Clone)]
+pub struct ExecutionContext {
+    /// Root directory that every step path resolves under.
+    pub dest: PathBuf,
+    /// Renders step paths, contents and URLs.
+    pub renderer: Renderer,
+    /// Bundle `files/` directories searched by `copy` steps, in order.
+    pub files: Vec<PathBuf>,
+    /// Serves `download` steps.
+    pub cache: HttpCache,
+    /// How step conditions split multi-valued arguments.
+    pub delimiters: ArgDelimiters,
+    /// Bar that command output is printed around while a template executes.
+    pub progress: Option<ProgressBar>,
+    /// Run `command`, `dependencies`, `download` and `git` steps; when unset they are skipped.
+    pub side_effects: bool,
+}
+
+impl Default for ExecutionContext {
+    fn default() -> Self {
+        ExecutionContext::new(".")
+    }
+}
+
+impl ExecutionContext {
+    pub fn new(dest: impl Into<PathBuf>) -> Self {
+        ExecutionContext {
+            dest: dest.into(),
+            renderer: Renderer::default(),
+            files: Vec::new(),
+            cache: HttpCache::new(cache_dir().join("http"), false),
+            delimiters: ArgDelimiters::default(),
+            progress: None,
+            side_effects: true,
+        }
+    }
+
+    /// Render and evaluate conditions with knowledge of the template's argument
+    /// types, and copy from its bundle files.
+    pub fn with_template(mut self, template: &Template) -> Self {
+        let strict = self.renderer.is_strict();
+        self.renderer = Renderer::for_args(&template.args);
+        self.renderer.set_strict(strict);
+        self.files = template.files.clone();
+        self.delimiters = ArgDelimiters::for_args(&template.args);
+        self
+    }
+
+    /// Download through `cache`, e.g. one that is offline.
+    pub fn with_cache(mut self, cache: HttpCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Fail steps that reference variables without a value.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.renderer.set_strict(strict);
+        self
+    }
+
+    /// Skip `command`, `dependencies`, `download` and `git` steps unless `allow` is set.
+    pub fn side_effects(mut self, allow: bool) -> Self {
+        self.side_effects = allow;
+        self
+    }
+
+    /// Print a line of a command's output without garbling the progress bar.
+    pub fn print_output(&self, line: &str) {
+        match &self.progress {
+            Some(progress) => progress.suspend(|| println!("  │ {}", line)),
+            None => println!("  │ {}", line),
+        }
+    }
+
+    /// Resolve a rendered step path under `dest`.
+    ///
+    /// Absolute paths, `..` components that climb above the root and
+    /// symlinks that point outside of it are rejected.
+    pub fn resolve(&self, path: &str) -> anyhow::Result<PathBuf> {
+        let mut relative = PathBuf::new();
+        for component in Path::new(path).components() {
+            match component {
+                Component::CurDir => {}
+                Component::Normal(part) => relative.push(part),
+                Component::ParentDir => {
+                    if !relative.pop() {
+                        anyhow::bail!("Path {} escapes the destination directory", path);
+                    }
+                }
+                Component::RootDir | Component::Prefix(_) => {
+                    anyhow::bail!("Absolute path {} is not allowed; step paths must be relative", path);
+                }
+            }
+        }
+
+        let resolved = self.dest.join(&relative);
+        self.check_symlinks(path, &resolved)?;
+        Ok(resolved)
+    }
+
+    /// Make sure the deepest existing ancestor of `resolved` is still inside `dest`
+    /// once symlinks are followed.
+    fn check_symlinks(&self, path: &str, resolved: &Path) -> anyhow::Result<()> {
+        let Ok(root) = self.dest.canonicalize() else {
+            // Nothing exists yet, so nothing can be a symlink
+            return Ok(());
+        };
+        let existing = resolved
+            .ancestors()
+            .find(|p| p.symlink_metadata().is_ok())
+            .unwrap_or(&self.dest);
+        let Ok(real) = existing.canonicalize() else {
+            anyhow::bail!("Path {} goes through a broken symlink", path);
+        };
+        if !real.starts_with(&root) {
+            anyhow::bail!("Path {} resolves outside the destination directory through a symlink", path);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use crate::bundle::sha256_hex;
+use super::context::ExecutionContext;
+use super::{ExecutionReport, Template};
+
+/// File written into the project root after scaffolding.
+pub const MANIFEST_FILE: &str = ".scaf.json";
+/// Pristine copies of the generated files, the common ancestor when `scaf update` merges.
+pub const BASE_DIR: &str = ".scaf/base";
+
+/// Records which template a project was generated from and what it produced,
+/// so later runs can audit, update or detect drift.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectManifest {
+    /// Version of scaf that generated the project.
+    pub scaf_version: String,
+    /// Template reference as it was given, e.g. a path, URL or `git+` source.
+    pub source: String,
+    pub name: String,
+    pub version: String,
+    /// SHA-256 of the resolved template, after `extends`.
+    pub template_hash: String,
+    /// Argument values, without those marked `secret`.
+    pub answers: BTreeMap<String, String>,
+    pub executed_steps: Vec<String>,
+    pub skipped_steps: Vec<String>,
+    /// SHA-256 of every generated file, keyed by its path relative to the project root.
+    pub files: BTreeMap<String, String>,
+}
+
+impl ProjectManifest {
+    pub fn new(template: &Template, source: &str, args_values: &HashMap<String, String>,
+               report: &ExecutionReport, ctx: &ExecutionContext) -> anyhow::Result<Self> {
+        let answers = args_values
+            .iter()
+            .filter(|(key, _)| !template.args.iter().any(|arg| arg.secret && &arg.key == *key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        let mut files = BTreeMap::new();
+        for path in &report.files {
+            if !path.is_file() {
+                continue;
+            }
+            files.insert(relative_key(&ctx.dest, path), sha256_hex(&std::fs::read(path)?));
+        }
+
+        Ok(ProjectManifest {
+            scaf_version: env!("CARGO_PKG_VERSION").to_string(),
+            source: source.to_string(),
+            name: template.name.clone(),
+            version: template.version.clone(),
+            template_hash: Self::template_hash(template)?,
+            answers,
+            executed_steps: report.executed.clone(),
+            skipped_steps: report.skipped.clone(),
+            files,
+        })
+    }
+
+    /// Hash of the template's JSON form. Object keys are sorted, so the hash
+    /// only changes when the template does.
+    pub fn template_hash(template: &Template) -> anyhow::Result<String> {
+        let value = serde_json::to_value(template)?;
+        Ok(sha256_hex(value.to_string().as_bytes()))
+    }
+
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}; was this project generated by scaf?", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Write the manifest into `dir` and replace the base snapshot with the
+    /// recorded files, read from `generated` (the directory they were rendered into).
+    pub fn save(&self, dir: &Path, generated: &Path) -> anyhow::Result<()> {
+        let base = dir.join(BASE_DIR);
+        if base.exists() {
+            std::fs::remove_dir_all(&base)?;
+        }
+        for relative in self.files.keys() {
+            let from = generated.join(relative);
+            if !from.is_file() {
+                continue;
+            }
+            let to = base.join(relative);
+            if let Some(parent) = to.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(&from, &to)?;
+        }
+
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        std::fs::write(dir.join(MANIFEST_FILE), content)?;
+        Ok(())
+    }
+}
+
+/// `path` relative to `root`, with `/` separators, as used for manifest keys.
+pub fn relative_key(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
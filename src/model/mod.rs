@@ -1,6 +1,13 @@
 pub mod template;
+pub mod answers;
 pub mod args;
+pub mod condition;
+pub mod context;
 pub mod language;
+pub mod manifest;
+pub mod package_manager;
+pub mod plan;
 pub mod step;
+pub mod validate;
 
 pub use template::*;
\ No newline at end of file
@@ -0,0 +1,138 @@
+use std::path::Path;
+use std::str::FromStr;
+
+/// A package manager a `dependencies` step can install packages with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Npm,
+    Yarn,
+    Pnpm,
+    Bun,
+    Cargo,
+    Pip,
+    Uv,
+    Poetry,
+    Go,
+}
+
+impl PackageManager {
+    pub const ALL: [PackageManager; 9] = [
+        PackageManager::Npm,
+        PackageManager::Yarn,
+        PackageManager::Pnpm,
+        PackageManager::Bun,
+        PackageManager::Cargo,
+        PackageManager::Pip,
+        PackageManager::Uv,
+        PackageManager::Poetry,
+        PackageManager::Go,
+    ];
+
+    /// Guess the package manager of the project in `dir` from its lockfile or manifest.
+    pub fn detect(dir: &Path) -> Option<Self> {
+        let markers = [
+            ("pnpm-lock.yaml", PackageManager::Pnpm),
+            ("yarn.lock", PackageManager::Yarn),
+            ("bun.lockb", PackageManager::Bun),
+            ("bun.lock", PackageManager::Bun),
+            ("package.json", PackageManager::Npm),
+            ("Cargo.toml", PackageManager::Cargo),
+            ("uv.lock", PackageManager::Uv),
+            ("poetry.lock", PackageManager::Poetry),
+            ("requirements.txt", PackageManager::Pip),
+            ("go.mod", PackageManager::Go),
+        ];
+        markers
+            .into_iter()
+            .find(|(file, _)| dir.join(file).is_file())
+            .map(|(_, manager)| manager)
+    }
+
+    /// The command that adds `packages` to the project, as dev dependencies if
+    /// `dev` is set. Managers without dev dependencies (pip, go) install them
+    /// like the others.
+    pub fn install_argv(&self, packages: &[String], dev: bool) -> Vec<String> {
+        let command: &[&str] = match (self, dev) {
+            (PackageManager::Npm, false) => &["npm", "install"],
+            (PackageManager::Npm, true) => &["npm", "install", "--save-dev"],
+            (PackageManager::Yarn, false) => &["yarn", "add"],
+            (PackageManager::Yarn, true) => &["yarn", "add", "--dev"],
+            (PackageManager::Pnpm, false) => &["pnpm", "add"],
+            (PackageManager::Pnpm, true) => &["pnpm", "add", "--save-dev"],
+            (PackageManager::Bun, false) => &["bun", "add"],
+            (PackageManager::Bun, true) => &["bun", "add", "--dev"],
+            (PackageManager::Cargo, false) => &["cargo", "add"],
+            (PackageManager::Cargo, true) => &["cargo", "add", "--dev"],
+            (PackageManager::Pip, _) => &["pip", "install"],
+            (PackageManager::Uv, false) => &["uv", "add"],
+            (PackageManager::Uv, true) => &["uv", "add", "--dev"],
+            (PackageManager::Poetry, false) => &["poetry", "add"],
+            (PackageManager::Poetry, true) => &["poetry", "add", "--group", "dev"],
+            (PackageManager::Go, _) => &["go", "get"],
+        };
+        let mut argv: Vec<String> = command.iter().map(|part| part.to_string()).collect();
+        argv.extend(packages.iter().map(|package| self.package_spec(package)));
+        argv
+    }
+
+    /// Translate `name@version` into the manager's own syntax. `version` is a
+    /// version or range (`1.2.3`, `^1.2`, `>=2, <3`); anything without `@`,
+    /// such as pip's `requests>=2`, is passed through. Bare versions get
+    /// Go's `v` prefix.
+    pub fn package_spec(&self, package: &str) -> String {
+        let Some((name, version)) = split_version(package) else {
+            return package.to_string();
+        };
+        match self {
+            PackageManager::Pip | PackageManager::Uv => {
+                if version.contains("://") {
+                    package.to_string()
+                } else if version.starts_with(['=', '<', '>', '~', '!']) {
+                    format!("{}{}", name, version)
+                } else {
+                    format!("{}=={}", name, version)
+                }
+            }
+            // Go module versions carry a `v` (`v1.2.3`).
+            PackageManager::Go if semver::Version::parse(version).is_ok() => format!("{}@v{}", name, version),
+            _ => format!("{}@{}", name, version),
+        }
+    }
+}
+
+/// Split `name@version`, leaving the `@` of npm scopes (`@types/node`) alone.
+fn split_version(package: &str) -> Option<(&str, &str)> {
+    let at = package.rfind('@').filter(|&at| at > 0)?;
+    let (name, version) = (&package[..at], &package[at + 1..]);
+    (!version.is_empty()).then_some((name, version))
+}
+
+impl FromStr for PackageManager {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PackageManager::ALL
+            .into_iter()
+            .find(|manager| manager.to_string() == s.trim().to_lowercase())
+            .ok_or_else(|| {
+                let known: Vec<String> = PackageManager::ALL.iter().map(|m| m.to_string()).collect();
+                anyhow::anyhow!("unknown package manager `{}` (expected one of {})", s, known.join(", "))
+            })
+    }
+}
+
+impl std::fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackageManager::Npm => write!(f, "npm"),
+            PackageManager::Yarn => write!(f, "yarn"),
+            PackageManager::Pnpm => write!(f, "pnpm"),
+            PackageManager::Bun => write!(f, "bun"),
+            PackageManager::Cargo => write!(f, "cargo"),
+            PackageManager::Pip => write!(f, "pip"),
+            PackageManager::Uv => write!(f, "uv"),
+            PackageManager::Poetry => write!(f, "poetry"),
+            PackageManager::Go => write!(f, "go"),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::utils::{shell_join, shell_quote};
+use super::context::ExecutionContext;
+use super::package_manager::PackageManager;
+use super::step::{StepType, TemplateStep};
+use super::Template;
+
+/// Lines of file content shown per step in a dry-run plan.
+const PREVIEW_LINES: usize = 10;
+
+/// What a step would do, resolved against the collected arguments but not executed.
+#[derive(Debug)]
+pub struct PlannedStep {
+    pub id: String,
+    pub description: String,
+    pub step_type: StepType,
+    /// Resolved target path, or the working directory for `Command` steps.
+    pub path: String,
+    /// Whether the step's conditions pass.
+    pub will_run: bool,
+    /// Rendered content preview for `File` and `Modify` steps, or the file list of `Copy` steps.
+    pub preview: Option<String>,
+    /// The exact process that would be spawned, shell-quoted.
+    pub command: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Template {
+    /// Resolve every step against `args_values` without touching disk.
+    pub fn plan(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext) -> Vec<PlannedStep> {
+        self.steps.iter().map(|step| step.plan(args_values, ctx)).collect()
+    }
+}
+
+impl TemplateStep {
+    pub fn plan(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext) -> PlannedStep {
+        let path = match self.step_type {
+            StepType::Command => match self.command_cwd(args_values, ctx) {
+                Ok(cwd) => cwd.display().to_string(),
+                Err(err) => err.to_string(),
+            },
+            StepType::Copy => ctx.dest.display().to_string(),
+            _ => match self.render("path", &self.path, args_values, ctx) {
+                Ok(rendered) => match ctx.resolve(&rendered) {
+                    Ok(path) => path.display().to_string(),
+                    Err(err) => format!("{} (rejected: {})", rendered, err),
+                },
+                Err(err) => err.to_string(),
+            },
+        };
+        let preview = match (&self.step_type, &self.content) {
+            (StepType::File | StepType::Modify, Some(content)) => Some(
+                self.render("content", content, args_values, ctx)
+                    .map(|rendered| preview(&rendered))
+                    .unwrap_or_else(|err| err.to_string()),
+            ),
+            (StepType::Copy, _) => Some(match self.copy_sources(args_values, ctx) {
+                Ok(pairs) => {
+                    let lines: Vec<String> = pairs
+                        .iter()
+                        .map(|(from, to)| format!("{} -> {}", from.display(), to.display()))
+                        .collect();
+                    preview(&lines.join("\n"))
+                }
+                Err(err) => err.to_string(),
+            }),
+            _ => None,
+        };
+        let command = match self.step_type {
+            StepType::Command => match (self.command_argv(args_values, ctx), self.command_env(args_values, ctx)) {
+                (Ok(argv), Ok(env)) => argv.map(|argv| {
+                    let assignments = env.iter().map(|(key, value)| format!("{}={} ", key, shell_quote(value)));
+                    format!("{}{}", assignments.collect::<String>(), shell_join(&argv))
+                }),
+                (Err(err), _) | (_, Err(err)) => Some(err.to_string()),
+            },
+            StepType::Git => Some(format!("cd {} && {}", shell_quote(&path), shell_join(&self.git_argv()))),
+            StepType::Dependencies => Some(match self.planned_installs(Path::new(&path), args_values, ctx) {
+                Ok(commands) => format!("cd {} && {}", shell_quote(&path), commands.join(" && ")),
+                Err(err) => err.to_string(),
+            }),
+            _ => None,
+        };
+        let url = match (&self.step_type, &self.url) {
+            (StepType::Download, Some(url)) => Some(
+                self.render("url", url, args_values, ctx).unwrap_or_else(|err| err.to_string()),
+            ),
+            _ => None,
+        };
+
+        PlannedStep {
+            id: self.id.clone(),
+            description: self.description.clone(),
+            step_type: self.step_type,
+            path,
+            will_run: self.check_condition(args_values, ctx),
+            preview,
+            command,
+            url,
+        }
+    }
+}
+
+impl TemplateStep {
+    /// The install commands of a `Dependencies` step. Without a configured
+    /// package manager, one that cannot be detected yet is left to run time,
+    /// since earlier steps may still create the manifest it is detected from.
+    fn planned_installs(&self, dir: &Path, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<Vec<String>> {
+        let detected = match self.configured_package_manager(args_values, ctx)? {
+            Some(manager) => Some(manager),
+            None => PackageManager::detect(dir),
+        };
+        Ok(self
+            .dependency_lists(args_values, ctx)?
+            .into_iter()
+            .map(|(packages, dev)| match detected {
+                Some(manager) => shell_join(&manager.install_argv(&packages, dev)),
+                None => format!(
+                    "<package manager detected at run time> {}{}",
+                    if dev { "(dev) " } else { "" },
+                    shell_join(&packages)
+                ),
+            })
+            .collect())
+    }
+}
+
+fn preview(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.len() <= PREVIEW_LINES {
+        return content.to_string();
+    }
+    format!(
+        "{}\n… ({} more lines)",
+        lines[..PREVIEW_LINES].join("\n"),
+        lines.len() - PREVIEW_LINES
+    )
+}
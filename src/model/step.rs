@@ -1,10 +1,21 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use anyhow::Context;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use tracing::{error, debug};
-use crate::utils::replace_args;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tracing::{debug, warn};
+use crate::utils::shell_join;
+use super::context::ExecutionContext;
+use super::condition::Conditions;
+use super::package_manager::PackageManager;
 use tokio::process::Command;
 
+/// Lines of a failed command's output quoted in the error.
+const OUTPUT_TAIL_LINES: usize = 20;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TemplateStep {
     pub id: String,
@@ -36,80 +47,60 @@ pub struct TemplateStep {
     pub template_engine: Option<String>,
     #[serde(default)]
     pub variables: Option<HashMap<String, String>>,
+    /// Working directory of a `command` step, relative to the destination.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Extra environment variables for a `command` step; values are rendered.
+    #[serde(default)]
+    pub env: Option<BTreeMap<String, String>>,
+    /// Kill a `command` step that runs longer than this.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Argv of a `command` step, run without a shell; each item is rendered.
+    #[serde(default)]
+    pub args: Option<Vec<String>>,
+    /// Insert a `modify` step's content after the line containing this marker
+    /// (or matching it, with `regex`).
+    #[serde(default)]
+    pub after: Option<String>,
+    /// Insert a `modify` step's content before the line containing this marker.
+    #[serde(default)]
+    pub before: Option<String>,
+    /// Match `after`/`before` as a regular expression instead of a literal marker.
+    #[serde(default)]
+    pub regex: bool,
+    /// Which of the lines matching `after`/`before` get the content.
+    #[serde(default)]
+    pub occurrence: Occurrence,
+    /// What a failing `command` or `git` step does to the scaffold.
+    #[serde(default)]
+    pub on_error: OnError,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// What to do when a `command` or `git` step fails or times out.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
-pub enum LogicalOperator {
-    And,
-    Or,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Conditions {
-    #[serde(default = "default_operator")]
-    pub operator: LogicalOperator,
-    pub conditions: Vec<Condition>,
-}
-
-fn default_operator() -> LogicalOperator {
-    LogicalOperator::And
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Condition {
-    pub field: String,
-    pub operator: ConditionOperator,
-    pub value: String,
+pub enum OnError {
+    /// Stop with an error naming the step.
+    #[default]
+    Fail,
+    /// Log a warning and carry on.
+    Warn,
+    /// Carry on without a word.
+    Ignore,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Which matches of a `modify` step's anchor to insert at.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
-pub enum ConditionOperator {
-    Equals,
-    NotEquals,
-    Contains,
-    StartsWith,
-    EndsWith,
-    In,
-    NotIn,
+pub enum Occurrence {
+    #[default]
+    First,
+    Last,
+    All,
 }
 
-impl Conditions {
-    pub fn evaluate(&self, args: &HashMap<String, String>) -> bool {
-        match self.operator {
-            LogicalOperator::And => self.conditions.iter().all(|c| c.evaluate(args)),
-            LogicalOperator::Or => self.conditions.iter().any(|c| c.evaluate(args)),
-        }
-    }
-}
-
-impl Condition {
-    pub fn evaluate(&self, args: &HashMap<String, String>) -> bool {
-        let field_value = match args.get(&self.field) {
-            Some(value) => value,
-            None => return false,
-        };
-
-        match self.operator {
-            ConditionOperator::Equals => field_value == &self.value,
-            ConditionOperator::NotEquals => field_value != &self.value,
-            ConditionOperator::Contains => field_value.contains(&self.value),
-            ConditionOperator::StartsWith => field_value.starts_with(&self.value),
-            ConditionOperator::EndsWith => field_value.ends_with(&self.value),
-            ConditionOperator::In => {
-                let field_values: Vec<&str> = field_value.split(',').map(str::trim).collect();
-                field_values.contains(&self.value.as_str())
-            },
-            ConditionOperator::NotIn => {
-                let field_values: Vec<&str> = field_value.split(',').map(str::trim).collect();
-                !field_values.contains(&self.value.as_str())
-            },
-        }
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum StepType {
     Directory,
@@ -118,11 +109,33 @@ pub enum StepType {
     Command,
     Git,
     Modify,
+    Copy,
+    Dependencies,
     // Template,
-    // Copy,
-    // Dependencies,
 }
 
+impl StepType {
+    /// Whether the step reaches beyond writing files: it runs a process or
+    /// fetches from the network.
+    pub fn has_side_effects(&self) -> bool {
+        matches!(self, StepType::Command | StepType::Download | StepType::Git | StepType::Dependencies)
+    }
+}
+
+impl std::fmt::Display for StepType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepType::Directory => write!(f, "directory"),
+            StepType::File => write!(f, "file"),
+            StepType::Download => write!(f, "download"),
+            StepType::Command => write!(f, "command"),
+            StepType::Git => write!(f, "git"),
+            StepType::Modify => write!(f, "modify"),
+            StepType::Copy => write!(f, "copy"),
+            StepType::Dependencies => write!(f, "dependencies"),
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -136,58 +149,155 @@ pub enum ModificationType {
 
 
 
+/// The leading components of a glob that contain no wildcards.
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect()
+}
+
 impl TemplateStep {
-    pub fn check_condition(&self, args: &HashMap<String, String>) -> bool {
+    pub fn check_condition(&self, args: &HashMap<String, String>, ctx: &ExecutionContext) -> bool {
         if let Some(conditions) = &self.conditions {
-            conditions.evaluate(args)
+            conditions.evaluate(args, &ctx.delimiters)
         } else {
             true
         }
     }
 
-    pub async fn execute(&self, args_values: &HashMap<String, String>) -> anyhow::Result<()> {
-        let path = replace_args(&self.path, args_values);
-        let path = PathBuf::from(path);
+    pub async fn execute(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<()> {
+        let path = self.render("path", &self.path, args_values, ctx)?;
+        let path = match self.step_type {
+            StepType::Command => self.command_cwd(args_values, ctx)?,
+            StepType::Copy => ctx.dest.clone(),
+            _ => ctx.resolve(&path)
+                .map_err(|err| anyhow::anyhow!("Step {}: {}", self.id, err))?,
+        };
 
         match self.step_type {
             StepType::Directory => self.create_dir(&path).await?,
-            StepType::File => self.add_file(&path, args_values).await?,
-            StepType::Download => self.download_file(&path, args_values).await?,
-            StepType::Command => self.execute_command(args_values).await?,
-            StepType::Git => self.git_command(&path).await?,
-            StepType::Modify => self.modify_file(&path, args_values).await?,
+            StepType::File => self.add_file(&path, args_values, ctx).await?,
+            StepType::Download => self.download_file(&path, args_values, ctx).await?,
+            StepType::Command => self.execute_command(&path, args_values, ctx).await?,
+            StepType::Git => self.git_command(&path, ctx).await?,
+            StepType::Modify => self.modify_file(&path, args_values, ctx).await?,
+            StepType::Copy => self.copy_files(args_values, ctx).await?,
+            StepType::Dependencies => self.install_dependencies(&path, args_values, ctx).await?,
             // StepType::Template => {}
-            // StepType::Copy => {}
-            // StepType::Dependencies => {}
         }
         Ok(())
     }
 
+    /// Render one of the step's templated fields, tagging errors with the step id.
+    pub fn render(&self, field: &str, source: &str, args_values: &HashMap<String, String>,
+                  ctx: &ExecutionContext) -> anyhow::Result<String> {
+        ctx.renderer.render(source, args_values)
+            .map_err(|err| anyhow::anyhow!("Step {}: failed to render {}: {}", self.id, field, err))
+    }
+
     pub(crate) async fn modify_file(&self, path: &PathBuf,
-                                    args_values: &HashMap<String, String>)
+                                    args_values: &HashMap<String, String>,
+                                    ctx: &ExecutionContext)
         -> anyhow::Result<()> {
-        let content = replace_args(&self.content.as_ref().unwrap_or(&String::new()), args_values);
+        let content = self.render("content", self.content.as_deref().unwrap_or_default(), args_values, ctx)?;
         let file_content = if tokio::fs::try_exists(path).await? {
             tokio::fs::read_to_string(path).await?
         } else {
             String::new()
         };
 
+        match self.modify(&file_content, &content, args_values, ctx)? {
+            Some(modified) => tokio::fs::write(path, modified).await?,
+            None => debug!("{} already has the content of step {}", path.display(), self.id),
+        }
+        Ok(())
+    }
+
+    /// `file_content` with the step's `content` inserted, or `None` if the
+    /// content is already there. Insertion points are the lines matching
+    /// `after`/`before`, or else come from `modification_type` and `line_number`.
+    /// The file keeps its trailing newline, if it had one.
+    ///
+    /// Only inserts are skipped, and only when the content's lines are already
+    /// whole lines of the file: right next to the anchor or line for `after`/`before`
+    /// and line-number inserts, anywhere for appends and prepends. Replacing always applies.
+    pub fn modify(&self, file_content: &str, content: &str, args_values: &HashMap<String, String>,
+                  ctx: &ExecutionContext) -> anyhow::Result<Option<String>> {
+        let block = content.strip_suffix('\n').unwrap_or(content);
+        let block_lines: Vec<&str> = block.lines().collect();
+        let anchor = self.after.as_ref().map(|a| ("after", a)).or(self.before.as_ref().map(|b| ("before", b)));
+
         let lines: Vec<&str> = file_content.lines().collect();
         let mut new_content = Vec::new();
 
-        if let Some(modification_type) = &self.modification_type {
-
+        if let Some((field, anchor)) = anchor {
+            let anchor = self.render(field, anchor, args_values, ctx)?;
+            let regex = if self.regex {
+                Some(Regex::new(&anchor)
+                    .map_err(|err| anyhow::anyhow!("Step {}: invalid {} pattern: {}", self.id, field, err))?)
+            } else {
+                None
+            };
+            let matches: Vec<usize> = lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| match &regex {
+                    Some(regex) => regex.is_match(line),
+                    None => line.contains(anchor.as_str()),
+                })
+                .map(|(i, _)| i)
+                .collect();
+            let targets: HashSet<usize> = match self.occurrence {
+                Occurrence::First => matches.first().copied().into_iter().collect(),
+                Occurrence::Last => matches.last().copied().into_iter().collect(),
+                Occurrence::All => matches.into_iter().collect(),
+            };
+            if targets.is_empty() {
+                anyhow::bail!("Step {}: no line matches `{}`", self.id, anchor);
+            }
+            let targets: HashSet<usize> = targets
+                .into_iter()
+                .filter(|&i| match field {
+                    "after" => !has_lines_at(&lines, &block_lines, i + 1),
+                    _ => i < block_lines.len() || !has_lines_at(&lines, &block_lines, i - block_lines.len()),
+                })
+                .collect();
+            if targets.is_empty() {
+                return Ok(None);
+            }
+            for (i, line) in lines.iter().enumerate() {
+                if field == "before" && targets.contains(&i) {
+                    new_content.push(block.to_string());
+                }
+                new_content.push(line.to_string());
+                if field == "after" && targets.contains(&i) {
+                    new_content.push(block.to_string());
+                }
+            }
+        } else {
+            let modification_type = self.modification_type.as_ref().unwrap_or(&ModificationType::Append);
+            let present = match (modification_type, self.line_number) {
+                (ModificationType::Replace, _) => false,
+                (ModificationType::InsertAfter, Some(line_num)) => has_lines_at(&lines, &block_lines, line_num),
+                (ModificationType::InsertBefore, Some(line_num)) => line_num > block_lines.len()
+                    && has_lines_at(&lines, &block_lines, line_num - 1 - block_lines.len()),
+                _ => (0..lines.len()).any(|start| has_lines_at(&lines, &block_lines, start)),
+            };
+            if present {
+                return Ok(None);
+            }
             match modification_type {
                 ModificationType::Append => {
                     new_content.extend(lines.iter().map(|&s| s.to_string()));
                     if !file_content.is_empty() {
                         new_content.push(String::new()); // Add newline before appending
                     }
-                    new_content.push(content.to_string());
+                    new_content.push(block.to_string());
                 },
                 ModificationType::Prepend => {
-                    new_content.push(content.to_string());
+                    new_content.push(block.to_string());
                     if !file_content.is_empty() {
                         new_content.push(String::new()); // Add newline after prepending
                     }
@@ -197,14 +307,14 @@ impl TemplateStep {
                     if let Some(line_num) = self.line_number {
                         for (i, line) in lines.iter().enumerate() {
                             if i + 1 == line_num {
-                                new_content.push(content.to_string());
+                                new_content.push(block.to_string());
                             } else {
                                 new_content.push(line.to_string());
                             }
                         }
                     } else {
                         // If no line number specified, replace entire file
-                        new_content.push(content.to_string());
+                        return Ok(Some(content.to_string()));
                     }
                 },
                 ModificationType::InsertAfter => {
@@ -212,34 +322,35 @@ impl TemplateStep {
                         for (i, line) in lines.iter().enumerate() {
                             new_content.push(line.to_string());
                             if i + 1 == line_num {
-                                new_content.push(content.to_string());
+                                new_content.push(block.to_string());
                             }
                         }
                     } else {
                         new_content.extend(lines.iter().map(|&s| s.to_string()));
-                        new_content.push(content.to_string());
+                        new_content.push(block.to_string());
                     }
                 },
                 ModificationType::InsertBefore => {
                     if let Some(line_num) = self.line_number {
                         for (i, line) in lines.iter().enumerate() {
                             if i + 1 == line_num {
-                                new_content.push(content.to_string());
+                                new_content.push(block.to_string());
                             }
                             new_content.push(line.to_string());
                         }
                     } else {
-                        new_content.push(content.to_string());
+                        new_content.push(block.to_string());
                         new_content.extend(lines.iter().map(|&s| s.to_string()));
                     }
                 },
             }
-
-
         }
 
-        tokio::fs::write(path, new_content.join("\n")).await?;
-        Ok(())
+        let mut modified = new_content.join("\n");
+        if file_content.ends_with('\n') || (file_content.is_empty() && content.ends_with('\n')) {
+            modified.push('\n');
+        }
+        Ok(Some(modified))
     }
 
     /// Create a directory
@@ -249,10 +360,11 @@ impl TemplateStep {
             .map_err(|_| anyhow::Error::msg("Failed to create directory"))
     }
     pub(crate) async fn add_file(&self, path: &PathBuf,
-                                    args_values: &HashMap<String, String>)
+                                    args_values: &HashMap<String, String>,
+                                    ctx: &ExecutionContext)
         -> anyhow::Result<()> {
         if let Some(content) = &self.content {
-            let content = replace_args(content, args_values);
+            let content = self.render("content", content, args_values, ctx)?;
             if let Some(parent) = path.parent() {
                 tokio::fs::create_dir_all(parent).await?;
             }
@@ -262,53 +374,355 @@ impl TemplateStep {
         Ok(())
     }
     pub(crate) async fn download_file(&self, path: &PathBuf,
-                                      args_values: &HashMap<String, String>)
+                                      args_values: &HashMap<String, String>,
+                                      ctx: &ExecutionContext)
         -> anyhow::Result<()> {
         if let Some(url) = &self.url {
-            let url = replace_args(url, args_values);
+            let url = self.render("url", url, args_values, ctx)?;
             debug!("Downloading from {} to {}", url, path.display());
             if let Some(parent) = path.parent() {
                 tokio::fs::create_dir_all(parent).await?;
             }
-            let response = reqwest::get(&url).await?;
-            let content = response.bytes().await?;
+            let url = reqwest::Url::parse(&url)
+                .map_err(|err| anyhow::anyhow!("Invalid download URL {}: {}", url, err))?;
+            let content = ctx.cache.get(&url).await?;
             tokio::fs::write(path, content).await?;
         }
         Ok(())
     }
-    pub(crate) async fn execute_command(&self, args_values: &HashMap<String, String>)
+    /// Bundle files a `copy` step reads and where each one ends up.
+    ///
+    /// `source` is a file, directory or glob relative to the bundle's `files/`
+    /// directory. A single file is copied to `path`; directories and globs are
+    /// copied below `path`, keeping their layout relative to the non-glob part
+    /// of `source`. File names are rendered too. When several bundles are
+    /// inherited, the first one providing a relative path wins.
+    pub fn copy_sources(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<Vec<(PathBuf, PathBuf)>> {
+        let source = self.source.as_deref()
+            .with_context(|| format!("Step {}: copy steps need a source", self.id))?;
+        let source = self.render("source", source, args_values, ctx)?;
+        let target = self.render("path", &self.path, args_values, ctx)?;
+        if ctx.files.is_empty() {
+            anyhow::bail!("Step {}: copy steps need a template bundle with a files/ directory", self.id);
+        }
+        if Path::new(&source).components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+            anyhow::bail!("Step {}: copy source {} must stay inside the bundle", self.id, source);
+        }
+
+        let is_glob = source.contains(['*', '?', '[']);
+        let mut seen = HashSet::new();
+        let mut pairs = Vec::new();
+        for root in &ctx.files {
+            let full = root.join(&source);
+            let (pattern, base) = if is_glob {
+                (root.join(&source), root.join(glob_base(&source)))
+            } else if full.is_dir() {
+                (full.join("**").join("*"), full.clone())
+            } else if full.is_file() {
+                let dest = ctx.resolve(&target)
+                    .map_err(|err| anyhow::anyhow!("Step {}: {}", self.id, err))?;
+                return Ok(vec![(full, dest)]);
+            } else {
+                continue;
+            };
+
+            let pattern = glob::Pattern::escape(&root.to_string_lossy())
+                + &pattern.to_string_lossy()[root.to_string_lossy().len()..];
+            let real_root = root.canonicalize()?;
+            for entry in glob::glob(&pattern)? {
+                let file = entry?;
+                if !file.is_file() {
+                    continue;
+                }
+                if !file.canonicalize()?.starts_with(&real_root) {
+                    anyhow::bail!("Step {}: {} points outside the bundle", self.id, file.display());
+                }
+                let relative = file.strip_prefix(&base)?.to_string_lossy().to_string();
+                if !seen.insert(relative.clone()) {
+                    continue;
+                }
+                let relative = self.render("source", &relative, args_values, ctx)?;
+                let dest = Path::new(&target).join(relative);
+                let dest = ctx.resolve(&dest.to_string_lossy())
+                    .map_err(|err| anyhow::anyhow!("Step {}: {}", self.id, err))?;
+                pairs.push((file, dest));
+            }
+        }
+
+        if pairs.is_empty() {
+            anyhow::bail!("Step {}: no bundle files match {}", self.id, source);
+        }
+        Ok(pairs)
+    }
+
+    /// Files this step writes, for recording in the project manifest.
+    pub fn outputs(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<Vec<PathBuf>> {
+        let resolve = || {
+            let path = self.render("path", &self.path, args_values, ctx)?;
+            ctx.resolve(&path).map_err(|err| anyhow::anyhow!("Step {}: {}", self.id, err))
+        };
+        Ok(match self.step_type {
+            StepType::File if self.content.is_some() => vec![resolve()?],
+            StepType::Download if self.url.is_some() => vec![resolve()?],
+            StepType::Modify => vec![resolve()?],
+            StepType::Copy => self.copy_sources(args_values, ctx)?
+                .into_iter()
+                .map(|(_, to)| to)
+                .collect(),
+            _ => Vec::new(),
+        })
+    }
+
+    /// Copy bundle files, rendering text files unless `template_engine` is `"none"`.
+    /// Binary files are copied byte for byte.
+    pub(crate) async fn copy_files(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
         -> anyhow::Result<()> {
-        if let Some(content) = &self.content {
-            let command = replace_args(content, args_values);
-            debug!("Running command: {}", command);
-            let output = Command::new("sh")
-                .arg("-c")
-                .arg(&command)
-                .output()
-                .await?;
-            if !output.status.success() {
-                error!("Command failed: {}", String::from_utf8_lossy(&output.stderr));
+        let render = self.template_engine.as_deref() != Some("none");
+        for (from, to) in self.copy_sources(args_values, ctx)? {
+            debug!("Copying {} to {}", from.display(), to.display());
+            let bytes = tokio::fs::read(&from).await?;
+            let bytes = match std::str::from_utf8(&bytes) {
+                Ok(text) if render && !text.contains('\0') => {
+                    let field = format!("file {}", from.display());
+                    self.render(&field, text, args_values, ctx)?.into_bytes()
+                }
+                _ => bytes,
+            };
+            if let Some(parent) = to.parent() {
+                tokio::fs::create_dir_all(parent).await?;
             }
+            tokio::fs::write(&to, bytes).await?;
+            let permissions = tokio::fs::metadata(&from).await?.permissions();
+            tokio::fs::set_permissions(&to, permissions).await?;
         }
         Ok(())
     }
-    pub(crate) async fn git_command(&self, path: &PathBuf)
+
+    /// The argv a `Command` step runs, after rendering: its `args` as given, or
+    /// its `content` run by `sh -c` with every substituted value shell-quoted.
+    pub fn command_argv(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<Option<Vec<String>>> {
+        if let Some(args) = self.args.as_ref().filter(|args| !args.is_empty()) {
+            let argv = args
+                .iter()
+                .enumerate()
+                .map(|(i, arg)| self.render(&format!("args[{}]", i), arg, args_values, ctx))
+                .collect::<anyhow::Result<_>>()?;
+            return Ok(Some(argv));
+        }
+        let Some(content) = &self.content else {
+            return Ok(None);
+        };
+        let command = ctx.renderer.render_shell(content, args_values)
+            .map_err(|err| anyhow::anyhow!("Step {}: failed to render content: {}", self.id, err))?;
+        Ok(Some(vec!["sh".to_string(), "-c".to_string(), command]))
+    }
+
+    /// The directory a `Command` step runs in: its `cwd` under the destination,
+    /// or the destination itself.
+    pub fn command_cwd(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<PathBuf> {
+        let Some(cwd) = &self.cwd else {
+            return Ok(ctx.dest.clone());
+        };
+        let cwd = self.render("cwd", cwd, args_values, ctx)?;
+        ctx.resolve(&cwd).map_err(|err| anyhow::anyhow!("Step {}: {}", self.id, err))
+    }
+
+    /// The rendered `env` of a `Command` or `Dependencies` step.
+    pub fn command_env(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<BTreeMap<String, String>> {
+        self.env
+            .iter()
+            .flatten()
+            .map(|(key, value)| Ok((key.clone(), self.render(&format!("env {}", key), value, args_values, ctx)?)))
+            .collect()
+    }
+
+    /// The argv a `Git` step runs inside its path.
+    pub fn git_argv(&self) -> Vec<String> {
+        let mut argv = vec!["git".to_string(), "init".to_string()];
+        if let Some(branch) = &self.branch {
+            argv.push("-b".to_string());
+            argv.push(branch.clone());
+        }
+        argv
+    }
+
+    pub(crate) async fn execute_command(&self, cwd: &Path,
+                                        args_values: &HashMap<String, String>,
+                                        ctx: &ExecutionContext)
         -> anyhow::Result<()> {
-        let output = Command::new("git")
-            .current_dir(&path)
-            .arg("init")
-            .args(if let Some(branch) = &self.branch {
-                vec!["-b", branch]
-            } else {
-                vec![]
-            })
-            .output()
-            .await?;
-        if !output.status.success() {
-            error!("Git init failed: {}", String::from_utf8_lossy(&output.stderr));
+        if let Some(argv) = self.command_argv(args_values, ctx)? {
+            let env = self.command_env(args_values, ctx)?;
+            self.run(&argv, cwd, &env, ctx).await?;
+        }
+        Ok(())
+    }
+    /// The package manager of a `Dependencies` step: its `package_manager`, the
+    /// `package_manager` argument, or the one whose lockfile or manifest is in `dir`.
+    pub fn package_manager(&self, dir: &Path, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<PackageManager> {
+        match self.configured_package_manager(args_values, ctx)? {
+            Some(manager) => Ok(manager),
+            None => PackageManager::detect(dir).with_context(|| format!(
+                "Step {}: no package manager given and none detected in {}; set `package_manager`",
+                self.id, dir.display()
+            )),
+        }
+    }
+
+    /// The package manager named by the step's `package_manager` or the
+    /// `package_manager` argument, if either is set.
+    pub(crate) fn configured_package_manager(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<Option<PackageManager>> {
+        let configured = match &self.package_manager {
+            Some(name) => Some(self.render("package_manager", name, args_values, ctx)?),
+            None => None,
+        };
+        let name = configured
+            .filter(|name| !name.trim().is_empty())
+            .or_else(|| args_values.get("package_manager").filter(|name| !name.trim().is_empty()).cloned());
+        name.map(|name| name.parse().map_err(|err| anyhow::anyhow!("Step {}: {}", self.id, err)))
+            .transpose()
+    }
+
+    /// The install commands of a `Dependencies` step run in `dir`: one for
+    /// `dependencies` and one for `dev_dependencies`, if they list anything.
+    pub fn dependency_argvs(&self, dir: &Path, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<Vec<Vec<String>>> {
+        let mut argvs = Vec::new();
+        for (packages, dev) in self.dependency_lists(args_values, ctx)? {
+            let manager = self.package_manager(dir, args_values, ctx)?;
+            argvs.push(manager.install_argv(&packages, dev));
+        }
+        Ok(argvs)
+    }
+
+    /// The rendered `dependencies` and `dev_dependencies` that list anything,
+    /// each with whether it holds dev dependencies.
+    pub(crate) fn dependency_lists(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<Vec<(Vec<String>, bool)>> {
+        let mut lists = Vec::new();
+        for (field, packages, dev) in [
+            ("dependencies", &self.dependencies, false),
+            ("dev_dependencies", &self.dev_dependencies, true),
+        ] {
+            let packages: Vec<String> = packages
+                .iter()
+                .flatten()
+                .map(|package| self.render(field, package, args_values, ctx))
+                .filter(|package| !matches!(package, Ok(package) if package.trim().is_empty()))
+                .collect::<anyhow::Result<_>>()?;
+            if !packages.is_empty() {
+                lists.push((packages, dev));
+            }
+        }
+        Ok(lists)
+    }
+
+    pub(crate) async fn install_dependencies(&self, path: &Path,
+                                             args_values: &HashMap<String, String>,
+                                             ctx: &ExecutionContext)
+        -> anyhow::Result<()> {
+        let env = self.command_env(args_values, ctx)?;
+        for argv in self.dependency_argvs(path, args_values, ctx)? {
+            self.run(&argv, path, &env, ctx).await?;
         }
         Ok(())
     }
+
+    pub(crate) async fn git_command(&self, path: &Path, ctx: &ExecutionContext)
+        -> anyhow::Result<()> {
+        self.run(&self.git_argv(), path, &BTreeMap::new(), ctx).await
+    }
+
+    /// Run `argv`, streaming its output, and apply `timeout_secs` and `on_error`.
+    async fn run(&self, argv: &[String], cwd: &Path, env: &BTreeMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<()> {
+        let command = shell_join(argv);
+        debug!("Running command in {}: {}", cwd.display(), command);
+        let result = self.run_streaming(argv, cwd, env, ctx)
+            .await
+            .map_err(|err| anyhow::anyhow!("Step {}: `{}` {}", self.id, command, err));
+        match (result, self.on_error) {
+            (Err(err), OnError::Warn) => {
+                warn!("{:#}", err);
+                Ok(())
+            }
+            (Err(err), OnError::Ignore) => {
+                debug!("Ignoring failure: {:#}", err);
+                Ok(())
+            }
+            (result, _) => result,
+        }
+    }
+
+    async fn run_streaming(&self, argv: &[String], cwd: &Path, env: &BTreeMap<String, String>,
+                           ctx: &ExecutionContext) -> anyhow::Result<()> {
+        let mut child = Command::new(&argv[0])
+            .args(&argv[1..])
+            .current_dir(cwd)
+            .envs(env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| anyhow::anyhow!("could not be started: {}", err))?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        let finished = async {
+            let (stdout, stderr, status) = tokio::join!(stream(stdout, ctx), stream(stderr, ctx), child.wait());
+            // Tools that report errors on stdout would otherwise fail without a word.
+            (if stderr.is_empty() { stdout } else { stderr }, status)
+        };
+        let (output, status) = match self.timeout_secs {
+            Some(secs) => match tokio::time::timeout(Duration::from_secs(secs), finished).await {
+                Ok(finished) => finished,
+                Err(_) => {
+                    child.kill().await.ok();
+                    anyhow::bail!("timed out after {}s", secs);
+                }
+            },
+            None => finished.await,
+        };
+        let status = status?;
+        if !status.success() {
+            let mut message = format!("failed ({})", status);
+            if !output.is_empty() {
+                message.push_str(":\n");
+                message.push_str(&Vec::from(output).join("\n"));
+            }
+            anyhow::bail!(message);
+        }
+        Ok(())
+    }
+}
+
+
+/// `block` is non-empty and makes up `lines[start..]`'s first lines, ignoring trailing whitespace.
+fn has_lines_at(lines: &[&str], block: &[&str], start: usize) -> bool {
+    !block.is_empty() && lines
+        .get(start..start + block.len())
+        .is_some_and(|found| found.iter().zip(block).all(|(line, own)| line.trim_end() == own.trim_end()))
+}
+
+/// Print each line of a child's output above the progress bar, returning the last few.
+async fn stream(reader: impl AsyncRead + Unpin, ctx: &ExecutionContext) -> VecDeque<String> {
+    let mut lines = BufReader::new(reader).lines();
+    let mut tail = VecDeque::new();
+    while let Ok(Some(line)) = lines.next_line().await {
+        ctx.print_output(&line);
+        if tail.len() == OUTPUT_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+    tail
 }
 
 
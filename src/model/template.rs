@@ -1,9 +1,13 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 use indicatif::{ProgressBar, ProgressStyle};
 use tracing::{warn, debug};
 use crate::utils::print::{print_section_footer, print_section_header};
-use super::args::TemplateArg;
+use super::answers::Answers;
+use super::context::ExecutionContext;
+use super::args::{ArgGroup, TemplateArg};
+use super::condition::{ArgDelimiters, Conditions};
 use super::language::Language;
 use super::step::TemplateStep;
 
@@ -20,6 +24,43 @@ pub struct ExtendArg {
     pub value: String,
 }
 
+/// What a call to [`Template::execute`] did.
+#[derive(Debug, Default)]
+pub struct ExecutionReport {
+    /// Ids of the steps that ran, in order.
+    pub executed: Vec<String>,
+    /// Ids of the steps whose conditions were not met.
+    pub skipped: Vec<String>,
+    /// Files written by `file`, `modify`, `download` and `copy` steps.
+    pub files: Vec<PathBuf>,
+}
+
+/// Group of args without a `group` of their own.
+pub const DEFAULT_GROUP: &str = "General";
+
+/// Args prompted together under one heading.
+#[derive(Debug)]
+pub struct ArgSection<'a> {
+    pub title: &'a str,
+    /// The declaration from `groups`, if the group has one.
+    pub group: Option<&'a ArgGroup>,
+    pub args: Vec<&'a TemplateArg>,
+}
+
+impl ArgSection<'_> {
+    /// Keys that must have a value before the section is asked: its group's
+    /// condition fields and what its args depend on, outside the section itself.
+    pub fn dependencies(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.group
+            .and_then(|group| group.conditions.as_ref())
+            .map(Conditions::fields)
+            .unwrap_or_default();
+        keys.extend(self.args.iter().flat_map(|arg| arg.dependencies()));
+        keys.retain(|key| !self.args.iter().any(|arg| arg.key == *key));
+        keys
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Template {
     #[serde(rename = "$schema")]
@@ -30,51 +71,155 @@ pub struct Template {
     pub author: String,
     pub language: Language,
     pub tags: Vec<String>,
+    /// Oldest scaf release able to run this template.
+    #[serde(default)]
+    pub min_scaf_version: Option<String>,
+    /// Titles, descriptions and conditions for the groups args refer to, in prompt order.
+    #[serde(default)]
+    pub groups: Vec<ArgGroup>,
     pub args: Vec<TemplateArg>,
     pub extends: Vec<TemplateExtend>,
     pub steps: Vec<TemplateStep>,
+    /// Bundle `files/` directories that `copy` steps read from, own first, then inherited.
+    #[serde(skip)]
+    pub files: Vec<PathBuf>,
 }
 
 impl Template {
-    pub async fn collect_arguments(&self) -> anyhow::Result<HashMap<String, String>> {
-        let mut args_values = HashMap::new();
-        let mut groups = HashMap::new();
-        for arg in &self.args {
-            // let group = arg.group.clone().unwrap_or_else(|| "General".to_string());
-            groups
-                .entry("General".to_string())
-                .or_insert_with(Vec::new)
-                .push(arg);
+    /// Put a parent's groups, args and steps ahead of this template's own.
+    /// Those redefined here (same id or key) replace the parent's,
+    /// and the parent's bundle files are searched after this template's.
+    pub fn inherit(&mut self, parent: Template) {
+        let mut groups: Vec<_> = parent.groups
+            .into_iter()
+            .filter(|group| !self.groups.iter().any(|own| own.id == group.id))
+            .collect();
+        groups.append(&mut self.groups);
+        self.groups = groups;
+
+        let mut args: Vec<_> = parent.args
+            .into_iter()
+            .filter(|arg| !self.args.iter().any(|own| own.key == arg.key))
+            .collect();
+        args.append(&mut self.args);
+        self.args = args;
+
+        let mut steps: Vec<_> = parent.steps
+            .into_iter()
+            .filter(|step| !self.steps.iter().any(|own| own.id == step.id))
+            .collect();
+        steps.append(&mut self.steps);
+        self.steps = steps;
+
+        self.files.extend(parent.files);
+    }
+
+    /// Args in prompt order: as declared, except that an arg always comes after
+    /// the args it depends on (see [`TemplateArg::dependencies`]). Args caught
+    /// in a dependency cycle keep their declared place.
+    pub fn ordered_args(&self) -> Vec<&TemplateArg> {
+        let index: HashMap<&str, usize> = self.args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| (arg.key.as_str(), i))
+            .collect();
+        let dependencies: Vec<Vec<usize>> = self.args
+            .iter()
+            .map(|arg| arg.dependencies().iter().filter_map(|dep| index.get(dep).copied()).collect())
+            .collect();
+        stable_order(&dependencies).into_iter().map(|i| &self.args[i]).collect()
+    }
+
+    /// Args by group: declared `groups` first, in order, then undeclared groups
+    /// in the order args (see [`Template::ordered_args`]) first name them. Args without a group go to
+    /// [`DEFAULT_GROUP`]; groups without args are left out. A section is moved
+    /// after the sections holding the args it depends on (see [`ArgSection::dependencies`]).
+    pub fn sections(&self) -> Vec<ArgSection<'_>> {
+        let mut sections: Vec<ArgSection> = self.groups
+            .iter()
+            .map(|group| ArgSection { title: &group.title, group: Some(group), args: Vec::new() })
+            .collect();
+        for arg in self.ordered_args() {
+            let id = arg.group.as_deref().unwrap_or(DEFAULT_GROUP);
+            let index = self.groups
+                .iter()
+                .position(|group| group.id == id)
+                .or_else(|| sections.iter().position(|s| s.group.is_none() && s.title == id));
+            match index {
+                Some(index) => sections[index].args.push(arg),
+                None => sections.push(ArgSection { title: id, group: None, args: vec![arg] }),
+            }
         }
+        sections.retain(|section| !section.args.is_empty());
 
-        for (group_name, args) in groups {
-            print_section_header(&group_name);
-
-            for arg in args {
-                if let Some(deps) = &arg.depends_on {
-                    let mut can_proceed = true;
-                    for dep in deps {
-                        if let Some(dep_value) = args_values.get(dep) {
-                            debug!("Dependency {} = {}", dep, dep_value);
-                        } else {
-                            debug!("Missing required dependency: {}", dep);
-                            can_proceed = false;
-                        }
-                    }
-                    if !can_proceed {
-                        continue;
-                    }
+        let owner: HashMap<&str, usize> = sections
+            .iter()
+            .enumerate()
+            .flat_map(|(i, section)| section.args.iter().map(move |arg| (arg.key.as_str(), i)))
+            .collect();
+        let dependencies: Vec<Vec<usize>> = sections
+            .iter()
+            .map(|section| section.dependencies().iter().filter_map(|key| owner.get(key).copied()).collect())
+            .collect();
+        let mut slots: Vec<Option<ArgSection>> = sections.into_iter().map(Some).collect();
+        stable_order(&dependencies)
+            .into_iter()
+            .map(|i| slots[i].take().expect("each section is placed once"))
+            .collect()
+    }
+
+    pub async fn collect_arguments(&self, answers: &Answers) -> anyhow::Result<HashMap<String, String>> {
+        for key in answers.keys() {
+            if !self.args.iter().any(|arg| &arg.key == key) {
+                warn!("Ignoring answer for unknown argument: {}", key);
+            }
+        }
+
+        let delimiters = ArgDelimiters::for_args(&self.args);
+        let mut args_values = HashMap::new();
+        for section in self.sections() {
+            let conditions = section.group.and_then(|group| group.conditions.as_ref());
+            if conditions.is_some_and(|c| !c.evaluate(&args_values, &delimiters)) {
+                debug!("Skipping group {} (conditions not met)", section.title);
+                for arg in section.args {
+                    skip(arg, answers, &mut args_values);
+                }
+                continue;
+            }
+            print_section_header(section.title);
+            if let Some(description) = section.group.and_then(|group| group.description.as_ref()) {
+                println!(" {}\n", description);
+            }
+
+            for arg in section.args {
+                if let Some(dep) = arg.depends_on.iter().flatten().find(|dep| !args_values.contains_key(*dep)) {
+                    debug!("Skipping {}: {} has no value", arg.key, dep);
+                    skip(arg, answers, &mut args_values);
+                    continue;
+                }
+                if arg.conditions.as_ref().is_some_and(|c| !c.evaluate(&args_values, &delimiters)) {
+                    debug!("Skipping {} (conditions not met)", arg.key);
+                    skip(arg, answers, &mut args_values);
+                    continue;
                 }
-                let value = arg.collect_args().await?;
+                let value = match arg.resolve_answer(answers)? {
+                    Some(value) => {
+                        debug!("Using supplied value for {}", arg.key);
+                        value
+                    }
+                    None => arg.collect_args().await?,
+                };
                 args_values.insert(arg.key.clone(), value.clone());
             }
             print_section_footer();
         }
         Ok(args_values)
     }
-    pub async fn execute(&self, args_values: &HashMap<String, String>) -> anyhow::Result<(usize, usize)> {
-        let mut executed_steps = 0;
-        let mut skipped_steps = 0;
+    pub async fn execute(&self, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+        -> anyhow::Result<ExecutionReport> {
+        tokio::fs::create_dir_all(&ctx.dest).await?;
+
+        let mut report = ExecutionReport::default();
         let total_steps = self.steps.len();
         let pb = ProgressBar::new(total_steps as u64);
         pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} ({percent}%) {msg}")?
@@ -85,18 +230,50 @@ impl Template {
         //     .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) {msg}")?
         //     .progress_chars("#>-"));
 
-        for step in &self.steps {
+        let ctx = &ExecutionContext { progress: Some(pb.clone()), ..ctx.clone() };
+        for (position, step) in self.steps.iter().enumerate() {
             pb.set_message(format!("Processing: {}", step.description));
-            if step.check_condition(&args_values) {
-                step.execute(&args_values).await?;
+            if step.step_type.has_side_effects() && !ctx.side_effects {
+                report.skipped.push(step.id.clone());
+                warn!("Skipping: {} (side effects not allowed)", step.description);
+            } else if step.check_condition(args_values, ctx) {
+                step.execute(args_values, ctx).await?;
+                report.files.extend(step.outputs(args_values, ctx)?);
+                report.executed.push(step.id.clone());
             } else {
-                skipped_steps += 1;
+                report.skipped.push(step.id.clone());
                 warn!("Skipping: {} (conditions not met)", step.description);
             }
-            executed_steps += 1;
-            pb.set_position(executed_steps as u64);
+            pb.set_position(position as u64 + 1);
         }
         pb.finish_with_message("Processing complete!");
-        Ok((executed_steps, skipped_steps))
+        Ok(report)
+    }
+}
+
+/// Indices `0..dependencies.len()` in their given order, except that each one
+/// comes after the indices it depends on. Members of a cycle keep their place.
+fn stable_order(dependencies: &[Vec<usize>]) -> Vec<usize> {
+    let mut placed = vec![false; dependencies.len()];
+    let mut order = Vec::with_capacity(dependencies.len());
+    while order.len() < dependencies.len() {
+        let ready = |i: usize| !placed[i] && dependencies[i].iter().all(|&j| placed[j] || j == i);
+        let next = (0..dependencies.len())
+            .find(|&i| ready(i))
+            .or_else(|| placed.iter().position(|done| !done))
+            .expect("an unplaced index remains");
+        placed[next] = true;
+        order.push(next);
+    }
+    order
+}
+
+/// Give an arg that is not asked its default, if it has one.
+fn skip(arg: &TemplateArg, answers: &Answers, args_values: &mut HashMap<String, String>) {
+    if answers.get(&arg.key).is_some() {
+        warn!("Ignoring the supplied value for {}: it is not asked with these answers", arg.key);
+    }
+    if !arg.default.is_empty() {
+        args_values.insert(arg.key.clone(), arg.default.clone());
     }
 }
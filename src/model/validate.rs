@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::utils::render::Renderer;
+use crate::version::{parse_requirement, parse_version};
+use super::args::ArgType;
+use super::condition::{ConditionOperator, Conditions};
+use super::package_manager::PackageManager;
+use super::step::StepType;
+use super::Template;
+
+/// A problem found while statically checking a template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    /// Where the problem is, e.g. `step create-app` or `arg project_name`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+impl Template {
+    /// Check the template for mistakes that would otherwise only show up while scaffolding.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let keys: HashSet<&str> = self.args.iter().map(|a| a.key.as_str()).collect();
+
+        if let Err(err) = parse_version(&self.version) {
+            issues.push(ValidationIssue { location: "version".to_string(), message: err.to_string() });
+        }
+        if let Some(Err(err)) = self.min_scaf_version.as_deref().map(parse_version) {
+            issues.push(ValidationIssue { location: "min_scaf_version".to_string(), message: err.to_string() });
+        }
+        for extend in &self.extends {
+            if let Err(err) = parse_requirement(&extend.version) {
+                issues.push(ValidationIssue {
+                    location: format!("extends {}", extend.template_id),
+                    message: err.to_string(),
+                });
+            }
+        }
+        self.validate_groups(&keys, &mut issues);
+        self.validate_args(&keys, &mut issues);
+        self.validate_sections(&mut issues);
+        self.validate_steps(&keys, &mut issues);
+        issues
+    }
+
+    fn validate_groups(&self, keys: &HashSet<&str>, issues: &mut Vec<ValidationIssue>) {
+        let mut seen = HashSet::new();
+        for group in &self.groups {
+            let location = format!("group {}", group.id);
+            if !seen.insert(group.id.as_str()) {
+                issues.push(ValidationIssue { location: location.clone(), message: "duplicate group id".to_string() });
+            }
+            for message in group.conditions.iter().flat_map(|c| condition_problems(c, keys)) {
+                issues.push(ValidationIssue { location: location.clone(), message });
+            }
+        }
+    }
+
+    fn validate_args(&self, keys: &HashSet<&str>, issues: &mut Vec<ValidationIssue>) {
+        let mut seen = HashSet::new();
+        for arg in &self.args {
+            let location = format!("arg {}", arg.key);
+            let mut report = |message: String| issues.push(ValidationIssue {
+                location: location.clone(),
+                message,
+            });
+
+            if !seen.insert(arg.key.as_str()) {
+                report("duplicate argument key".to_string());
+            }
+            if let Some(pattern) = &arg.pattern {
+                if let Err(err) = regex::Regex::new(pattern) {
+                    report(format!("invalid pattern `{}`: {}", pattern, err));
+                }
+            }
+            if let (ArgType::Enum, Some(values)) = (&arg.arg_type, &arg.values) {
+                let defaults: Vec<&str> = if arg.multiple {
+                    arg.default
+                        .split(arg.delimiter.as_deref().unwrap_or(","))
+                        .map(str::trim)
+                        .filter(|d| !d.is_empty())
+                        .collect()
+                } else if arg.default.is_empty() {
+                    vec![]
+                } else {
+                    vec![arg.default.as_str()]
+                };
+                for default in defaults {
+                    if !values.iter().any(|v| v.value == default) {
+                        report(format!("default `{}` is not one of the allowed values", default));
+                    }
+                }
+            }
+            for dep in arg.depends_on.iter().flatten() {
+                if !keys.contains(dep.as_str()) {
+                    report(format!("depends on unknown argument `{}`", dep));
+                }
+            }
+            for message in arg.conditions.iter().flat_map(|c| condition_problems(c, keys)) {
+                report(message);
+            }
+        }
+
+        for cycle in self.dependency_cycles() {
+            issues.push(ValidationIssue {
+                location: format!("arg {}", cycle[0]),
+                message: format!("dependency cycle: {}", cycle.join(" -> ")),
+            });
+        }
+    }
+
+    /// Group conditions and dependencies that point at args asked no earlier than
+    /// themselves, which the section order cannot satisfy.
+    fn validate_sections(&self, issues: &mut Vec<ValidationIssue>) {
+        let sections = self.sections();
+        let position: HashMap<&str, usize> = sections
+            .iter()
+            .enumerate()
+            .flat_map(|(i, section)| section.args.iter().map(move |arg| (arg.key.as_str(), i)))
+            .collect();
+        for (i, section) in sections.iter().enumerate() {
+            if let Some(group) = section.group {
+                for field in group.conditions.iter().flat_map(Conditions::fields) {
+                    if position.get(field).is_some_and(|&j| j >= i) {
+                        issues.push(ValidationIssue {
+                            location: format!("group {}", group.id),
+                            message: format!("condition refers to `{}`, which is not asked before the group", field),
+                        });
+                    }
+                }
+            }
+            for arg in &section.args {
+                for dep in arg.dependencies() {
+                    if position.get(dep).is_some_and(|&j| j > i) {
+                        issues.push(ValidationIssue {
+                            location: format!("arg {}", arg.key),
+                            message: format!("depends on `{}`, which is asked in a later group", dep),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn validate_steps(&self, keys: &HashSet<&str>, issues: &mut Vec<ValidationIssue>) {
+        let renderer = Renderer::for_args(&self.args);
+        let mut seen = HashSet::new();
+        for step in &self.steps {
+            let location = format!("step {}", step.id);
+            let mut report = |message: String| issues.push(ValidationIssue {
+                location: location.clone(),
+                message,
+            });
+
+            if !seen.insert(step.id.as_str()) {
+                report("duplicate step id".to_string());
+            }
+            if step.step_type == StepType::Copy && step.source.is_none() {
+                report("copy step has no source".to_string());
+            }
+            if step.step_type == StepType::Command {
+                match &step.args {
+                    Some(args) if args.is_empty() => report("command step has empty args".to_string()),
+                    Some(_) if step.content.is_some() => {
+                        report("command step has both content and args".to_string())
+                    }
+                    None if step.content.is_none() => report("command step has no content or args".to_string()),
+                    _ => {}
+                }
+            }
+            if step.step_type == StepType::Modify {
+                if step.after.is_some() && step.before.is_some() {
+                    report("modify step has both after and before".to_string());
+                }
+                let anchor = step.after.as_ref().or(step.before.as_ref());
+                if anchor.is_some() && (step.modification_type.is_some() || step.line_number.is_some()) {
+                    report("modify step combines an anchor with modification_type or line_number".to_string());
+                }
+                if let Some(pattern) = anchor.filter(|a| step.regex && !a.contains("{{")) {
+                    if let Err(err) = regex::Regex::new(pattern) {
+                        report(format!("invalid pattern `{}`: {}", pattern, err));
+                    }
+                }
+            }
+            if step.step_type == StepType::Dependencies {
+                if step.dependencies.iter().chain(&step.dev_dependencies).all(Vec::is_empty) {
+                    report("dependencies step lists no packages".to_string());
+                }
+                if let Some(manager) = step.package_manager.as_deref().filter(|m| !m.contains("{{")) {
+                    if let Err(err) = manager.parse::<PackageManager>() {
+                        report(err.to_string());
+                    }
+                }
+            }
+
+            let mut fields = vec![("path".to_string(), &step.path)];
+            let optional = [
+                ("content", step.content.as_ref()),
+                ("url", step.url.as_ref()),
+                ("source", step.source.as_ref()),
+            ];
+            fields.extend(optional.into_iter().filter_map(|(field, text)| Some((field.to_string(), text?))));
+            for (field, anchor) in [("after", &step.after), ("before", &step.before)] {
+                if let Some(anchor) = anchor {
+                    fields.push((field.to_string(), anchor));
+                }
+            }
+            if let Some(manager) = &step.package_manager {
+                fields.push(("package_manager".to_string(), manager));
+            }
+            for (field, items) in [("args", &step.args), ("dependencies", &step.dependencies),
+                                   ("dev_dependencies", &step.dev_dependencies)] {
+                for (i, item) in items.iter().flatten().enumerate() {
+                    fields.push((format!("{}[{}]", field, i), item));
+                }
+            }
+            for (field, text) in fields {
+                match renderer.variables(text) {
+                    Ok(names) => {
+                        for name in names {
+                            if !keys.contains(name.as_str()) {
+                                report(format!("{} uses unknown placeholder `{{{{ {} }}}}`", field, name));
+                            }
+                        }
+                    }
+                    Err(err) => report(format!("{} is not a valid template: {}", field, err)),
+                }
+            }
+
+            for message in step.conditions.iter().flat_map(|c| condition_problems(c, keys)) {
+                report(message);
+            }
+        }
+    }
+
+    /// Find cycles among `depends_on` and condition fields, each reported once
+    /// as the path that closes it.
+    fn dependency_cycles(&self) -> Vec<Vec<String>> {
+        let graph: HashMap<&str, Vec<&str>> = self.args
+            .iter()
+            .map(|arg| (arg.key.as_str(), arg.dependencies()))
+            .collect();
+
+        let mut cycles = Vec::new();
+        let mut done = HashSet::new();
+        for arg in &self.args {
+            let mut stack = Vec::new();
+            visit(arg.key.as_str(), &graph, &mut stack, &mut done, &mut cycles);
+        }
+        cycles
+    }
+}
+
+/// Unknown fields, bad regexes and bad version requirements in `conditions`.
+fn condition_problems(conditions: &Conditions, keys: &HashSet<&str>) -> Vec<String> {
+    let mut problems = Vec::new();
+    for condition in conditions.leaves() {
+        if !keys.contains(condition.field.as_str()) {
+            problems.push(format!("condition refers to unknown argument `{}`", condition.field));
+        }
+        match condition.operator {
+            ConditionOperator::Matches => {
+                if let Err(err) = regex::Regex::new(&condition.value) {
+                    problems.push(format!("invalid pattern `{}` in condition: {}", condition.value, err));
+                }
+            }
+            ConditionOperator::Satisfies => {
+                if let Err(err) = parse_requirement(&condition.value) {
+                    problems.push(format!("invalid condition on `{}`: {}", condition.field, err));
+                }
+            }
+            _ => {}
+        }
+    }
+    problems
+}
+
+fn visit<'a>(
+    key: &'a str,
+    graph: &HashMap<&'a str, Vec<&'a str>>,
+    stack: &mut Vec<&'a str>,
+    done: &mut HashSet<&'a str>,
+    cycles: &mut Vec<Vec<String>>,
+) {
+    if done.contains(key) {
+        return;
+    }
+    if let Some(start) = stack.iter().position(|k| *k == key) {
+        let mut cycle: Vec<String> = stack[start..].iter().map(|k| k.to_string()).collect();
+        cycle.push(key.to_string());
+        cycles.push(cycle);
+        return;
+    }
+    stack.push(key);
+    for dep in graph.get(key).into_iter().flatten() {
+        visit(dep, graph, stack, done, cycles);
+    }
+    stack.pop();
+    done.insert(key);
+}
@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+use dialoguer::FuzzySelect;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use crate::bundle::{ArchiveKind, MANIFEST};
+use crate::config::Config;
+use crate::model::Template;
+use crate::registry::{RegistryClient, SearchQuery};
+
+/// Directory under the scaf home holding local templates (JSON files, bundle
+/// directories or bundle archives).
+pub const TEMPLATES_DIR: &str = "templates";
+/// File under the scaf home listing recently used templates, newest first.
+pub const RECENT_FILE: &str = "recent.json";
+const MAX_RECENT: usize = 10;
+/// The most templates the registry returns per page.
+const REGISTRY_PAGE_SIZE: u32 = 50;
+
+/// Where a [`Candidate`] was found.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Origin {
+    #[default]
+    Recent,
+    Alias,
+    Local,
+    Registry,
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::Recent => write!(f, "recent"),
+            Origin::Alias => write!(f, "alias"),
+            Origin::Local => write!(f, "local"),
+            Origin::Registry => write!(f, "registry"),
+        }
+    }
+}
+
+/// A template offered by `scaf new` when none is given.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Candidate {
+    /// What `scaf new` would be given to load it.
+    pub reference: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(skip)]
+    pub origin: Origin,
+}
+
+impl Candidate {
+    fn from_template(reference: String, template: &Template, origin: Origin) -> Self {
+        Candidate {
+            reference,
+            name: template.name.clone(),
+            description: template.description.clone(),
+            language: Some(template.language.to_string()),
+            tags: template.tags.clone(),
+            origin,
+        }
+    }
+
+    /// One line for the picker: name, description, language and tags.
+    pub fn label(&self) -> String {
+        let mut label = self.name.clone();
+        if !self.description.is_empty() {
+            label.push_str(&format!(" — {}", self.description));
+        }
+        let mut details: Vec<String> = self.language.iter().cloned().collect();
+        details.extend(self.tags.iter().cloned());
+        if !details.is_empty() {
+            label.push_str(&format!(" [{}]", details.join(", ")));
+        }
+        format!("{} ({}: {})", label, self.origin, self.reference)
+    }
+}
+
+/// Templates found in `dir`: `*.json` files, bundle directories holding a
+/// `template.json`, and `.tar.gz`/`.zip` bundles. Unreadable files are skipped.
+pub fn local_templates(dir: &Path) -> Vec<Candidate> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| Some(entry.ok()?.path())).collect();
+    paths.sort();
+
+    let mut candidates = Vec::new();
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let reference = path.to_string_lossy().into_owned();
+        let manifest = if path.is_dir() {
+            path.join(MANIFEST)
+        } else if ArchiveKind::from_name(&name).is_some() {
+            candidates.push(Candidate { reference, name, origin: Origin::Local, ..Default::default() });
+            continue;
+        } else if name.ends_with(".json") {
+            path.clone()
+        } else {
+            continue;
+        };
+        let parsed = std::fs::read_to_string(&manifest)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_json::from_str::<Template>(&content)?));
+        match parsed {
+            Ok(template) => candidates.push(Candidate::from_template(reference, &template, Origin::Local)),
+            Err(err) => debug!("Ignoring {}: {}", path.display(), err),
+        }
+    }
+    candidates
+}
+
+/// Recently used templates recorded in `path`, newest first.
+pub fn recent_templates(path: &Path) -> Vec<Candidate> {
+    let Ok(content) = std::fs::read_to_string(path) else { return Vec::new() };
+    match serde_json::from_str::<Vec<Candidate>>(&content) {
+        Ok(recent) => recent,
+        Err(err) => {
+            debug!("Ignoring {}: {}", path.display(), err);
+            Vec::new()
+        }
+    }
+}
+
+/// Put `template`, loaded from `reference`, at the top of the recent list in `path`.
+pub fn record_recent(path: &Path, reference: &str, template: &Template) -> anyhow::Result<()> {
+    let mut recent = recent_templates(path);
+    recent.retain(|candidate| candidate.reference != reference);
+    recent.insert(0, Candidate::from_template(reference.to_string(), template, Origin::Recent));
+    recent.truncate(MAX_RECENT);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(&recent)?)?;
+    Ok(())
+}
+
+/// Everything the picker offers: recent templates, aliases, templates in the
+/// scaf home and the registry's listing, without duplicate references.
+/// An unreachable registry only costs its entries.
+pub async fn candidates(config: &Config, home: &Path) -> Vec<Candidate> {
+    let mut all = recent_templates(&home.join(RECENT_FILE));
+    all.extend(config.aliases.iter().map(|(alias, target)| Candidate {
+        reference: alias.clone(),
+        name: alias.clone(),
+        description: target.clone(),
+        origin: Origin::Alias,
+        ..Default::default()
+    }));
+    all.extend(local_templates(&home.join(TEMPLATES_DIR)));
+
+    let query = SearchQuery { limit: REGISTRY_PAGE_SIZE, ..Default::default() };
+    let listing = match RegistryClient::from_config(config) {
+        Ok(client) => client.search(&query).await,
+        Err(err) => Err(err),
+    };
+    match listing {
+        Ok(page) => all.extend(page.data.into_iter().map(|summary| Candidate {
+            reference: summary.id,
+            name: summary.name,
+            description: summary.description,
+            language: summary.language,
+            tags: summary.tags,
+            origin: Origin::Registry,
+        })),
+        Err(err) => warn!("Could not list registry templates: {:#}", err),
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    all.retain(|candidate| seen.insert(candidate.reference.clone()));
+    all
+}
+
+/// Let the user fuzzy-search `candidates` and return the chosen reference.
+pub fn pick(candidates: &[Candidate]) -> anyhow::Result<String> {
+    if candidates.is_empty() {
+        anyhow::bail!("No templates found; pass a template reference to `scaf new`");
+    }
+    let labels: Vec<String> = candidates.iter().map(Candidate::label).collect();
+    let index = FuzzySelect::new()
+        .with_prompt("Template")
+        .items(&labels)
+        .default(0)
+        .interact()?;
+    Ok(candidates[index].reference.clone())
+}
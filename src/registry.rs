@@ -0,0 +1,153 @@
+use anyhow::Context;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use crate::cache::HttpCache;
+use crate::config::Config;
+use crate::model::Template;
+
+/// Owner of templates published without a username.
+pub const DEFAULT_OWNER: &str = "scaf";
+
+/// A template as listed by the registry, without its args and steps.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateSummary {
+    /// `username/id`, usable wherever a template reference is expected.
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, rename = "createdBy")]
+    pub author: String,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub downloads: Option<u64>,
+}
+
+/// A page of results from a list or search call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub pagination: Pagination,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+    pub total: u64,
+    pub page: u32,
+    pub limit: u32,
+    pub total_pages: u32,
+}
+
+impl<T> Page<T> {
+    pub fn has_next(&self) -> bool {
+        self.pagination.page < self.pagination.total_pages
+    }
+}
+
+/// Filters for [`RegistryClient::search`]; all of them are optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    /// Matched by the registry against names and descriptions.
+    pub text: Option<String>,
+    /// The registry cannot filter by tag or language; these are applied to
+    /// the page it returns.
+    pub tags: Vec<String>,
+    pub language: Option<String>,
+    pub page: u32,
+    /// Page size; the registry's own default when 0.
+    pub limit: u32,
+}
+
+impl SearchQuery {
+    fn matches(&self, template: &TemplateSummary) -> bool {
+        let language = self.language.as_deref().is_none_or(|wanted| {
+            template.language.as_deref().is_some_and(|language| language.eq_ignore_ascii_case(wanted))
+        });
+        language && self.tags.iter().all(|tag| template.tags.iter().any(|own| own.eq_ignore_ascii_case(tag)))
+    }
+}
+
+/// Typed client for the template registry API:
+///
+/// - `GET /api/v1/template?search=&page=&limit=` lists and searches,
+/// - `GET /api/v1/template/{username}/{id}` returns a template, or `null`.
+///
+/// Templates without a username live under `scaf`. The registry serves one
+/// version of each template and does not list older ones.
+///
+/// Responses go through the [`HttpCache`], so templates load offline once
+/// they have been fetched.
+#[derive(Debug, Clone)]
+pub struct RegistryClient {
+    base_url: Url,
+    cache: HttpCache,
+}
+
+impl RegistryClient {
+    pub fn new(base_url: &str, cache: HttpCache) -> anyhow::Result<Self> {
+        let base_url = Url::parse(&format!("{}/", base_url.trim_end_matches('/')))
+            .with_context(|| format!("Invalid registry URL {}", base_url))?;
+        Ok(RegistryClient { base_url, cache })
+    }
+
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        Self::new(config.registry_url(), HttpCache::from_config(config))
+    }
+
+    /// URL of a template given as `id` or `username/id`.
+    pub fn template_url(&self, id: &str) -> anyhow::Result<Url> {
+        let (owner, name) = split_id(id)?;
+        Ok(self.base_url.join(&format!("api/v1/template/{}/{}", owner, name))?)
+    }
+
+    /// List templates, optionally filtered. An empty query lists everything.
+    pub async fn search(&self, query: &SearchQuery) -> anyhow::Result<Page<TemplateSummary>> {
+        let mut params: Vec<(&str, String)> = Vec::new();
+        if let Some(text) = query.text.as_deref().filter(|t| !t.is_empty()) {
+            params.push(("search", text.to_string()));
+        }
+        params.push(("page", query.page.max(1).to_string()));
+        if query.limit > 0 {
+            params.push(("limit", query.limit.to_string()));
+        }
+        let mut url = self.base_url.join("api/v1/template")?;
+        url.query_pairs_mut().extend_pairs(&params);
+        let mut page: Page<TemplateSummary> = self.get_json(&url).await?;
+        page.data.retain(|template| query.matches(template));
+        Ok(page)
+    }
+
+    /// The full template, with args and steps.
+    pub async fn get(&self, id: &str) -> anyhow::Result<Template> {
+        let url = self.template_url(id)?;
+        // The registry answers `null` for templates it does not know.
+        let template: Option<Template> = self.get_json(&url).await?;
+        template.ok_or_else(|| anyhow::anyhow!("Template {} was not found in the registry", id))
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &Url) -> anyhow::Result<T> {
+        debug!("GET {}", url);
+        let body = self.cache.get(url).await
+            .with_context(|| format!("Registry request {} failed", url))?;
+        serde_json::from_slice(&body)
+            .with_context(|| format!("Unexpected response from registry {}", url))
+    }
+}
+
+/// Split `username/id` (or a bare `id` owned by `scaf`).
+fn split_id(id: &str) -> anyhow::Result<(&str, &str)> {
+    let parts: Vec<&str> = id.split('/').collect();
+    match parts.as_slice() {
+        [name] if !name.is_empty() => Ok((DEFAULT_OWNER, name)),
+        [owner, name] if !owner.is_empty() && !name.is_empty() => Ok((owner, name)),
+        _ => anyhow::bail!("Invalid template id `{}`, expected `id` or `username/id`", id),
+    }
+}
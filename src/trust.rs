@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::Confirm;
+use tracing::{debug, warn};
+use crate::config::Config;
+use crate::loader::TemplateSource;
+use crate::model::context::ExecutionContext;
+use crate::model::step::StepType;
+use crate::model::Template;
+use crate::utils::print::display_side_effects;
+use crate::version::parse_version;
+
+/// A step that reaches beyond writing files: a command, a package install, a download
+/// or a git operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SideEffect {
+    pub step: String,
+    pub step_type: StepType,
+    /// The rendered command, install commands, URL or git invocation.
+    pub detail: String,
+}
+
+/// What to do with side effects, from `--allow-commands` and `--no-commands`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SideEffectPolicy {
+    /// Run them if the source is trusted, otherwise ask.
+    #[default]
+    Ask,
+    Allow,
+    Skip,
+}
+
+impl SideEffectPolicy {
+    pub fn from_flags(allow: bool, deny: bool) -> Self {
+        match (allow, deny) {
+            (_, true) => SideEffectPolicy::Skip,
+            (true, false) => SideEffectPolicy::Allow,
+            (false, false) => SideEffectPolicy::Ask,
+        }
+    }
+}
+
+/// The side effects of the steps whose conditions pass, resolved against `args_values`.
+pub fn side_effects(template: &Template, args_values: &HashMap<String, String>, ctx: &ExecutionContext)
+    -> Vec<SideEffect> {
+    template
+        .plan(args_values, ctx)
+        .into_iter()
+        .filter(|step| step.will_run && step.step_type.has_side_effects())
+        .map(|step| SideEffect {
+            detail: step.command.or(step.url).unwrap_or(step.path),
+            step: step.id,
+            step_type: step.step_type,
+        })
+        .collect()
+}
+
+/// Whether a template from `source` may run commands without asking.
+///
+/// Local files are trusted. Anything else must come from an origin listed under
+/// `trusted` in the config; registry templates also have to be pinned to an
+/// exact version, so a new release cannot slip in new commands.
+pub fn is_trusted(source: &TemplateSource, config: &Config) -> bool {
+    let matches = |origin: &str| config.trusted.iter().any(|trusted| origin_matches(origin, trusted));
+    match source {
+        TemplateSource::File(_) => true,
+        TemplateSource::Registry { registry, requirement, .. } => {
+            parse_version(requirement).is_ok() && matches(registry)
+        }
+        // Unpinned registry templates are fetched by URL; they can change under us.
+        TemplateSource::Url(url) => {
+            !url.as_str().starts_with(&format!("{}/api/", config.registry_url())) && matches(url.as_str())
+        }
+        TemplateSource::Git(git) => matches(&git.url),
+    }
+}
+
+/// `origin` is `trusted` itself or lies below it.
+fn origin_matches(origin: &str, trusted: &str) -> bool {
+    let trusted = trusted.trim_end_matches('/');
+    match origin.strip_prefix(trusted) {
+        Some(rest) => !trusted.is_empty() && (rest.is_empty() || rest.starts_with(['/', '#', '?', '@'])),
+        None => false,
+    }
+}
+
+/// Decide whether `effects` may run: `Ok(true)` to run them, `Ok(false)` to
+/// skip them, or an error if they were refused. `sources` are the template and
+/// everything it extends; unless all of them are trusted, the effects need
+/// `--allow-commands` or a confirmation, which is impossible non-interactively.
+pub fn authorize(effects: &[SideEffect], sources: &[TemplateSource], config: &Config,
+                 policy: SideEffectPolicy, non_interactive: bool) -> anyhow::Result<bool> {
+    if effects.is_empty() {
+        return Ok(true);
+    }
+    match policy {
+        SideEffectPolicy::Allow => return Ok(true),
+        SideEffectPolicy::Skip => {
+            warn!("Skipping {} step(s) with side effects (--no-commands)", effects.len());
+            return Ok(false);
+        }
+        SideEffectPolicy::Ask => {}
+    }
+    let Some(untrusted) = sources.iter().find(|source| !is_trusted(source, config)) else {
+        debug!("Every template source is trusted; running the side effects");
+        return Ok(true);
+    };
+
+    display_side_effects(untrusted, effects);
+    if non_interactive {
+        anyhow::bail!(
+            "Template {} is not trusted and wants to run commands, install packages, download or use git; \
+             pass --allow-commands to run them, --no-commands to skip them, \
+             or add its origin to `trusted` in the config",
+            untrusted
+        );
+    }
+    let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt("Run these steps?")
+        .default(false)
+        .interact()?;
+    if !confirmed {
+        anyhow::bail!("Aborted; pass --no-commands to scaffold without these steps");
+    }
+    Ok(true)
+}
@@ -0,0 +1,253 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use tracing::{debug, warn};
+use crate::model::context::ExecutionContext;
+use crate::model::manifest::{relative_key, ProjectManifest, BASE_DIR};
+use crate::model::step::{StepType, TemplateStep};
+use crate::model::{ExecutionReport, Template};
+
+/// Appended to the name of a file the new version is written next to.
+pub const NEW_SUFFIX: &str = ".scaf-new";
+
+/// What `scaf update` did to the project.
+#[derive(Debug, Default)]
+pub struct UpdateReport {
+    /// Files the new template generates for the first time.
+    pub added: Vec<String>,
+    /// Files left untouched by the user, replaced with the new version.
+    pub updated: Vec<String>,
+    /// Files changed on both sides whose changes merged cleanly.
+    pub merged: Vec<String>,
+    /// Files written with conflict markers (or kept as-is, for binary files).
+    pub conflicts: Vec<String>,
+    /// Files the template no longer generates and the user had not changed.
+    pub removed: Vec<String>,
+    /// `command`, `git`, `dependencies` and `modify` steps that ran because they are new.
+    pub steps: Vec<String>,
+}
+
+/// Re-apply `template` to the project in `ctx.dest`, previously generated as `previous`.
+///
+/// The template is rendered into a staging directory and each generated file is
+/// merged three ways: the copy in `.scaf/base` (what was generated last time),
+/// the project's current file and the newly rendered file. `command`, `git` and
+/// `dependencies` steps, and `modify` steps on files the template does not generate itself,
+/// only run when they were not executed before.
+pub async fn update(
+    template: &Template,
+    source: &str,
+    previous: &ProjectManifest,
+    args_values: &HashMap<String, String>,
+    ctx: &ExecutionContext,
+) -> anyhow::Result<UpdateReport> {
+    let staging = std::env::temp_dir().join(format!(
+        "scaf-update-{}-{}",
+        std::process::id(),
+        crate::bundle::sha256_hex(ctx.dest.to_string_lossy().as_bytes())
+    ));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    let result = update_in(template, source, previous, args_values, ctx, &staging).await;
+    if let Err(err) = std::fs::remove_dir_all(&staging) {
+        debug!("Failed to remove {}: {}", staging.display(), err);
+    }
+    result
+}
+
+async fn update_in(
+    template: &Template,
+    source: &str,
+    previous: &ProjectManifest,
+    args_values: &HashMap<String, String>,
+    ctx: &ExecutionContext,
+    staging: &Path,
+) -> anyhow::Result<UpdateReport> {
+    let staging_ctx = ExecutionContext {
+        dest: staging.to_path_buf(),
+        ..ctx.clone()
+    };
+    tokio::fs::create_dir_all(staging).await?;
+
+    let mut rendered = ExecutionReport::default();
+    let mut pending: Vec<&TemplateStep> = Vec::new();
+    // Files changed by earlier `modify` steps that are not re-rendered: keep their previous record.
+    let mut carried = BTreeSet::new();
+    for step in &template.steps {
+        if !step.check_condition(args_values, ctx) {
+            rendered.skipped.push(step.id.clone());
+            continue;
+        }
+        let is_new = !previous.executed_steps.contains(&step.id);
+        if step.step_type.has_side_effects() && !ctx.side_effects {
+            // Not allowed to run: keep what an earlier run did, skip what would be new.
+            if is_new {
+                rendered.skipped.push(step.id.clone());
+                continue;
+            }
+            if step.step_type == StepType::Download {
+                let path = step.render("path", &step.path, args_values, ctx)?;
+                let target = staging_ctx.resolve(&path)
+                    .map_err(|err| anyhow::anyhow!("Step {}: {}", step.id, err))?;
+                carried.insert(relative_key(staging, &target));
+            }
+            rendered.executed.push(step.id.clone());
+            continue;
+        }
+        match step.step_type {
+            StepType::Command | StepType::Git | StepType::Dependencies => {
+                if is_new {
+                    pending.push(step);
+                } else {
+                    rendered.executed.push(step.id.clone());
+                }
+            }
+            StepType::Modify => {
+                let path = step.render("path", &step.path, args_values, ctx)?;
+                let target = staging_ctx.resolve(&path)
+                    .map_err(|err| anyhow::anyhow!("Step {}: {}", step.id, err))?;
+                if target.exists() {
+                    step.execute(args_values, &staging_ctx).await?;
+                    rendered.files.extend(step.outputs(args_values, &staging_ctx)?);
+                    rendered.executed.push(step.id.clone());
+                } else if is_new {
+                    pending.push(step);
+                } else {
+                    carried.insert(relative_key(staging, &target));
+                    rendered.executed.push(step.id.clone());
+                }
+            }
+            _ => {
+                step.execute(args_values, &staging_ctx).await?;
+                rendered.files.extend(step.outputs(args_values, &staging_ctx)?);
+                rendered.executed.push(step.id.clone());
+            }
+        }
+    }
+
+    let mut manifest = ProjectManifest::new(template, source, args_values, &rendered, &staging_ctx)?;
+    let mut report = UpdateReport::default();
+    let base_dir = ctx.dest.join(BASE_DIR);
+    let paths: BTreeSet<&String> = previous.files.keys().chain(manifest.files.keys()).collect();
+    for relative in paths {
+        if carried.contains(relative) {
+            continue;
+        }
+        let target = ctx.resolve(relative)?;
+        let base = read_optional(&base_dir.join(relative))?;
+        let ours = read_optional(&target)?;
+        let theirs = read_optional(&staging.join(relative))?;
+        apply(relative, &target, base, ours, theirs, &mut report).await?;
+    }
+
+    // Keep the previous record and base copy of files only earlier `modify` steps touched.
+    for relative in &carried {
+        if let Some(hash) = previous.files.get(relative) {
+            manifest.files.insert(relative.clone(), hash.clone());
+            let base = base_dir.join(relative);
+            if base.is_file() {
+                let to = staging.join(relative);
+                if let Some(parent) = to.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::copy(base, to)?;
+            }
+        }
+    }
+
+    for step in pending {
+        debug!("Running new step {}", step.id);
+        step.execute(args_values, ctx).await?;
+        rendered.executed.push(step.id.clone());
+        report.steps.push(step.id.clone());
+    }
+
+    let executed: HashSet<&String> = rendered.executed.iter().collect();
+    manifest.executed_steps = template.steps
+        .iter()
+        .filter(|step| executed.contains(&step.id))
+        .map(|step| step.id.clone())
+        .collect();
+    manifest.save(&ctx.dest, staging)?;
+    Ok(report)
+}
+
+fn read_optional(path: &Path) -> anyhow::Result<Option<Vec<u8>>> {
+    if path.is_file() {
+        Ok(Some(std::fs::read(path)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Bring one file up to date given its base, current and newly generated contents.
+async fn apply(
+    relative: &str,
+    target: &Path,
+    base: Option<Vec<u8>>,
+    ours: Option<Vec<u8>>,
+    theirs: Option<Vec<u8>>,
+    report: &mut UpdateReport,
+) -> anyhow::Result<()> {
+    let write = |bytes: Vec<u8>| async move {
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(target, bytes).await
+    };
+
+    match (base, ours, theirs) {
+        (_, Some(ours), Some(theirs)) if ours == theirs => {}
+        (None, None, Some(theirs)) => {
+            write(theirs).await?;
+            report.added.push(relative.to_string());
+        }
+        (Some(base), None, Some(theirs)) => {
+            if base != theirs {
+                warn!("{} was deleted but the template changed it; leaving it deleted", relative);
+            }
+        }
+        (Some(base), Some(ours), Some(theirs)) if ours == base => {
+            write(theirs).await?;
+            report.updated.push(relative.to_string());
+        }
+        (None, Some(_), Some(theirs)) => {
+            // Without a base there is nothing to merge against: keep the file and
+            // leave the new version next to it.
+            let mut new = target.as_os_str().to_owned();
+            new.push(NEW_SUFFIX);
+            tokio::fs::write(&new, theirs).await?;
+            warn!("{} has no base copy; the new version is in {}{}", relative, relative, NEW_SUFFIX);
+            report.conflicts.push(relative.to_string());
+        }
+        (Some(base), Some(ours), Some(theirs)) => {
+            if base == theirs {
+                return Ok(());
+            }
+            if [&base, &ours, &theirs].iter().any(|bytes| bytes.contains(&0)) {
+                warn!("{} is binary and changed on both sides; keeping the current file", relative);
+                report.conflicts.push(relative.to_string());
+                return Ok(());
+            }
+            match diffy::merge_bytes(&base, &ours, &theirs) {
+                Ok(merged) => {
+                    write(merged).await?;
+                    report.merged.push(relative.to_string());
+                }
+                Err(conflicted) => {
+                    write(conflicted).await?;
+                    report.conflicts.push(relative.to_string());
+                }
+            }
+        }
+        (Some(base), Some(ours), None) if ours == base => {
+            tokio::fs::remove_file(target).await?;
+            report.removed.push(relative.to_string());
+        }
+        (_, Some(_), None) => {
+            debug!("{} is no longer generated; keeping the modified file", relative);
+        }
+        (_, None, None) => {}
+    }
+    Ok(())
+}
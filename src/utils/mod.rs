@@ -1,7 +1,8 @@
-use std::collections::HashMap;
+use std::path::PathBuf;
 use tracing::Level;
 
 pub(crate) mod print;
+pub mod render;
 
 #[allow(dead_code)]
 fn format_log_message(message: &str, level: &str) -> String {
@@ -29,10 +30,39 @@ pub(crate) fn init_logger() {
 }
 
 
-pub(crate) fn replace_args(content: &str, args: &HashMap<String, String>) -> String {
-    let mut result = content.to_string();
-    for (key, value) in args {
-        result = result.replace(&format!("{{{{ {} }}}}", key), value);
+/// Root of scaf's cache (`$SCAF_CACHE_DIR`, or e.g. `~/.cache/scaf`).
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("SCAF_CACHE_DIR") {
+        return PathBuf::from(dir);
     }
-    result
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("scaf")
+}
+
+/// scaf's own directory for local templates and history (`$SCAF_HOME`, or `~/.scaf`).
+pub fn scaf_home() -> PathBuf {
+    if let Some(dir) = std::env::var_os("SCAF_HOME") {
+        return PathBuf::from(dir);
+    }
+    dirs::home_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(".scaf")
+}
+
+/// Quote `value` for a POSIX shell, leaving plain words untouched.
+pub fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty() && value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_./=:@,+%".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Render an argv as a copy-pasteable shell command line.
+pub fn shell_join(argv: &[String]) -> String {
+    argv.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" ")
 }
@@ -1,5 +1,14 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::info;
+use crate::cache::{dir_size, CacheEntry};
+use crate::git::CHECKOUT_MARKER;
+use crate::loader::TemplateSource;
 use crate::model::Template;
+use crate::model::plan::PlannedStep;
+use crate::model::step::StepType;
+use crate::registry::{Page, TemplateSummary};
+use crate::trust::SideEffect;
 
 pub(crate) fn print_section_header(title: &str) {
     println!(
@@ -26,4 +35,131 @@ pub(crate) fn display_template_info(template: &Template) {
 
     info!("Steps       : {} steps to execute", template.steps.len());
     print_section_footer();
-}
\ No newline at end of file
+}
+
+pub(crate) fn display_side_effects(source: &TemplateSource, effects: &[SideEffect]) {
+    print_section_header("Side effects");
+    println!(" {} is not trusted. Besides writing files, it will:", source);
+    for effect in effects {
+        let action = match effect.step_type {
+            StepType::Command => "run",
+            StepType::Download => "download",
+            StepType::Dependencies => "install",
+            _ => "git",
+        };
+        println!("   [{}] {:<8} {}", effect.step, action, effect.detail);
+    }
+    print_section_footer();
+}
+
+pub(crate) fn display_plan(plan: &[PlannedStep]) {
+    print_section_header("Plan (dry run)");
+    for (i, step) in plan.iter().enumerate() {
+        let status = if step.will_run { "run" } else { "skip (conditions not met)" };
+        println!("{:>3}. [{}] {} — {}", i + 1, step.step_type, step.id, step.description);
+        println!("     status  : {}", status);
+        println!("     path    : {}", step.path);
+        if let Some(url) = &step.url {
+            println!("     url     : {}", url);
+        }
+        if let Some(command) = &step.command {
+            println!("     command : {}", command);
+        }
+        if let Some(preview) = &step.preview {
+            println!("     content :");
+            for line in preview.lines() {
+                println!("       │ {}", line);
+            }
+        }
+    }
+    let runs = plan.iter().filter(|s| s.will_run).count();
+    println!("\n {} of {} steps would run; nothing was written.", runs, plan.len());
+    print_section_footer();
+}
+
+pub(crate) fn display_search_results(page: &Page<TemplateSummary>) {
+    print_section_header("Templates");
+    if page.data.is_empty() {
+        info!("No templates found");
+    }
+    for template in &page.data {
+        println!(" {} ({}) — {}", template.id, template.version, template.name);
+        if !template.description.is_empty() {
+            println!("     {}", template.description);
+        }
+        let mut details = Vec::new();
+        if let Some(language) = &template.language {
+            details.push(language.clone());
+        }
+        if !template.tags.is_empty() {
+            details.push(template.tags.join(", "));
+        }
+        if let Some(downloads) = template.downloads {
+            details.push(format!("{} downloads", downloads));
+        }
+        if !details.is_empty() {
+            println!("     {}", details.join(" · "));
+        }
+    }
+    let pagination = &page.pagination;
+    println!("\n Page {} of {} · {} template(s) in total", pagination.page, pagination.total_pages, pagination.total);
+    if page.has_next() {
+        println!(" More results with --page {}", pagination.page + 1);
+    }
+    print_section_footer();
+}
+
+pub(crate) fn display_template_details(template: &Template) {
+    display_template_info(template);
+    if !template.args.is_empty() {
+        print_section_header("Arguments");
+        for section in template.sections() {
+            println!(" {}", section.title);
+            for arg in section.args {
+                let required = if arg.required { " (required)" } else { "" };
+                println!("   {} — {}{}", arg.key, arg.name, required);
+                if let Some(description) = &arg.description {
+                    println!("       {}", description);
+                }
+            }
+        }
+        print_section_footer();
+    }
+}
+
+pub(crate) fn display_cache(root: &Path, entries: &[CacheEntry]) {
+    print_section_header("Cache");
+    println!(" {}", root.display());
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    for entry in entries {
+        let age = now.saturating_sub(entry.fetched_at);
+        println!(" {} — {} bytes, fetched {} ago", entry.url, entry.size, format_age(age));
+    }
+    for (kind, dir) in [("bundle", root.join("bundles")), ("git", root.join("git"))] {
+        let Ok(children) = std::fs::read_dir(&dir) else { continue };
+        for child in children.filter_map(Result::ok) {
+            let path = child.path();
+            let label = std::fs::read_to_string(path.join(".git").join(CHECKOUT_MARKER))
+                .unwrap_or_else(|_| child.file_name().to_string_lossy().into_owned());
+            println!(" {} {} — {} bytes", kind, label, dir_size(&path));
+        }
+    }
+    if entries.is_empty() && !root.exists() {
+        info!("The cache is empty");
+    } else {
+        println!("\n {} bytes in total", dir_size(root));
+    }
+    print_section_footer();
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
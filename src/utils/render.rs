@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use heck::{ToKebabCase, ToLowerCamelCase, ToSnakeCase, ToUpperCamelCase};
+use minijinja::syntax::SyntaxConfig;
+use minijinja::value::{Enumerator, Object, ObjectRepr, Value};
+use minijinja::{Environment, Error, ErrorKind, State, UndefinedBehavior};
+use crate::model::args::{ArgType, TemplateArg};
+use crate::utils::shell_quote;
+
+/// How an argument's string value is exposed to templates.
+#[derive(Debug, Clone)]
+enum ValueKind {
+    Boolean,
+    Number,
+    /// Multi-valued argument split on its delimiter.
+    List(String),
+}
+
+/// Renders `content`, `path` and `url` fields with Jinja-style syntax:
+/// `{{ key | filter }}`, `{% if %}` and `{% for %}` blocks.
+///
+/// `\{{`, `\{%` and `\{#` produce the literal delimiter, as does anything
+/// inside `{% raw %}…{% endraw %}`.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    env: Environment<'static>,
+    /// Same as `env`, but shell-quotes every printed value.
+    shell_env: Environment<'static>,
+    kinds: HashMap<String, ValueKind>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        let env = environment();
+        let mut shell_env = env.clone();
+        shell_env.set_formatter(|out, _state, value| {
+            let text = value.to_string();
+            let text = if value.is_safe() { text } else { shell_quote(&text) };
+            out.write_str(&text).map_err(Error::from)
+        });
+        // Values the template already put in quotes are escaped for those quotes instead.
+        shell_env.add_filter(IN_SINGLE_QUOTES, |state: &State, value: Value| {
+            escape_unsafe(state, value, |text| text.replace('\'', "'\\''"))
+        });
+        shell_env.add_filter(IN_DOUBLE_QUOTES, |state: &State, value: Value| {
+            escape_unsafe(state, value, |text| {
+                text.chars().fold(String::with_capacity(text.len()), |mut escaped, c| {
+                    if matches!(c, '\\' | '"' | '$' | '`') {
+                        escaped.push('\\');
+                    }
+                    escaped.push(c);
+                    escaped
+                })
+            })
+        });
+
+        Renderer {
+            env,
+            shell_env,
+            kinds: HashMap::new(),
+        }
+    }
+}
+
+/// The environment both renderers share: syntax options and case filters.
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_syntax(
+        SyntaxConfig::builder()
+            .keep_trailing_newline(true)
+            .trim_blocks(true)
+            .lstrip_blocks(true)
+            .build()
+            .expect("default delimiters are valid"),
+    );
+    env.add_filter("snake_case", |value: String| value.to_snake_case());
+    env.add_filter("kebab_case", |value: String| value.to_kebab_case());
+    env.add_filter("pascal_case", |value: String| value.to_upper_camel_case());
+    env.add_filter("camel_case", |value: String| value.to_lower_camel_case());
+    env.add_filter("shell_quote", |value: String| Value::from_safe_string(shell_quote(&value)));
+    env
+}
+
+impl Renderer {
+    /// A renderer that knows the argument types, so booleans, numbers and
+    /// multi-valued arguments behave as such in `{% if %}` and `{% for %}`.
+    pub fn for_args(args: &[TemplateArg]) -> Self {
+        let mut renderer = Renderer::default();
+        for arg in args {
+            let kind = if arg.multiple {
+                ValueKind::List(arg.delimiter.clone().unwrap_or_else(|| ",".to_string()))
+            } else {
+                match arg.arg_type {
+                    ArgType::Boolean => ValueKind::Boolean,
+                    ArgType::Number => ValueKind::Number,
+                    _ => continue,
+                }
+            };
+            renderer.kinds.insert(arg.key.clone(), kind);
+        }
+        renderer
+    }
+
+    /// Fail on variables that are printed or iterated but have no value,
+    /// instead of rendering them as empty. Truthiness checks such as
+    /// `{% if optional %}` and the `default` filter keep working.
+    pub fn set_strict(&mut self, strict: bool) {
+        let behavior = if strict {
+            UndefinedBehavior::SemiStrict
+        } else {
+            UndefinedBehavior::Lenient
+        };
+        self.env.set_undefined_behavior(behavior);
+        self.shell_env.set_undefined_behavior(behavior);
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.env.undefined_behavior() == UndefinedBehavior::SemiStrict
+    }
+
+    pub fn render(&self, source: &str, args_values: &HashMap<String, String>) -> anyhow::Result<String> {
+        self.render_in(false, source, args_values)
+    }
+
+    /// Render a shell command line: every printed value is shell-quoted, so an
+    /// answer such as `x; rm -rf ~` stays a single word. Values inside quotes
+    /// written in the template (`"for {{ name }}"`) are escaped for those quotes
+    /// instead, and values passed through `| safe` are inserted as-is.
+    pub fn render_shell(&self, source: &str, args_values: &HashMap<String, String>) -> anyhow::Result<String> {
+        self.render_in(true, source, args_values)
+    }
+
+    fn render_in(&self, shell: bool, source: &str, args_values: &HashMap<String, String>)
+        -> anyhow::Result<String> {
+        if !has_syntax(source) {
+            return Ok(source.to_string());
+        }
+        let (env, source) = if shell {
+            (&self.shell_env, quote_in_context(&unescape(source)))
+        } else {
+            (&self.env, unescape(source))
+        };
+        let context: HashMap<&str, Value> = args_values
+            .iter()
+            .map(|(key, value)| (key.as_str(), self.to_value(key, value)))
+            .collect();
+        match env.render_str(&source, context) {
+            Ok(rendered) => Ok(rendered),
+            Err(err) if err.kind() == ErrorKind::UndefinedError => {
+                let unresolved: Vec<String> = self.variables(&source)?
+                    .into_iter()
+                    .filter(|name| !args_values.contains_key(name))
+                    .collect();
+                if unresolved.is_empty() {
+                    // Something like `{{ name.missing }}`, where the variable itself is set.
+                    let failing = err.range()
+                        .and_then(|range| source.get(range))
+                        .map(str::trim)
+                        .filter(|expr| ![IN_SINGLE_QUOTES, IN_DOUBLE_QUOTES].contains(expr));
+                    match (failing, err.line()) {
+                        (Some(expr), Some(line)) => anyhow::bail!("unresolved value `{}` on line {}", expr, line),
+                        (Some(expr), None) => anyhow::bail!("unresolved value `{}`", expr),
+                        (None, Some(line)) => anyhow::bail!("unresolved value on line {}", line),
+                        (None, None) => return Err(err.into()),
+                    }
+                }
+                anyhow::bail!("unresolved variables: {}", unresolved.join(", "))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Top-level variables a template reads, sorted by name.
+    pub fn variables(&self, source: &str) -> anyhow::Result<Vec<String>> {
+        if !has_syntax(source) {
+            return Ok(Vec::new());
+        }
+        let source = unescape(source);
+        let template = self.env.template_from_str(&source)?;
+        let mut names: Vec<String> = template.undeclared_variables(false).into_iter().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn to_value(&self, key: &str, value: &str) -> Value {
+        match self.kinds.get(key) {
+            Some(ValueKind::Boolean) => Value::from(value.eq_ignore_ascii_case("true")),
+            Some(ValueKind::Number) => {
+                if let Ok(int) = value.parse::<i64>() {
+                    Value::from(int)
+                } else if let Ok(float) = value.parse::<f64>() {
+                    Value::from(float)
+                } else {
+                    Value::from(value)
+                }
+            }
+            Some(ValueKind::List(delimiter)) => Value::from_object(MultiValue::new(value, delimiter)),
+            None => Value::from(value),
+        }
+    }
+}
+
+fn has_syntax(source: &str) -> bool {
+    source.contains("{{") || source.contains("{%") || source.contains("{#")
+}
+
+/// Turn `\{{`-style escapes into expressions that print the bare delimiter,
+/// marked safe so that `render_shell` does not quote it.
+fn unescape(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if let Some(end) = raw_block_end(rest) {
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if c == '\\' && tag_close(&rest[1..]).is_some() {
+            out.push_str(&format!("{{{{ '{}' | safe }}}}", &rest[1..3]));
+            rest = &rest[3..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+/// Filters that [`quote_in_context`] routes quoted values through.
+const IN_SINGLE_QUOTES: &str = "shell_in_single_quotes";
+const IN_DOUBLE_QUOTES: &str = "shell_in_double_quotes";
+
+/// `escape(value)` as a safe string, unless `value` is already safe, or is
+/// undefined and left for strict mode to reject.
+fn escape_unsafe(state: &State, value: Value, escape: impl Fn(&str) -> String) -> Value {
+    let strict = state.undefined_behavior() != UndefinedBehavior::Lenient;
+    if value.is_safe() || (value.is_undefined() && strict) {
+        value
+    } else {
+        Value::from_safe_string(escape(&value.to_string()))
+    }
+}
+
+/// Pipe every `{{ … }}` of a shell command that sits inside single or double
+/// quotes through the filter escaping for those quotes. Bare ones are left to
+/// the quoting formatter, and `{% raw %}` blocks are copied untouched.
+fn quote_in_context(source: &str) -> String {
+    #[derive(Clone, Copy)]
+    enum Quotes {
+        None,
+        Single,
+        Double,
+    }
+
+    let mut out = String::with_capacity(source.len());
+    let mut quotes = Quotes::None;
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if let Some(end) = raw_block_end(rest) {
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        if let Some(close) = tag_close(rest) {
+            let end = rest[2..].find(close).map_or(rest.len(), |i| i + 4);
+            let tag = &rest[..end];
+            match (close, quotes) {
+                ("}}", Quotes::Single) => out.push_str(&through_filter(tag, IN_SINGLE_QUOTES)),
+                ("}}", Quotes::Double) => out.push_str(&through_filter(tag, IN_DOUBLE_QUOTES)),
+                _ => out.push_str(tag),
+            }
+            rest = &rest[end..];
+            continue;
+        }
+
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+        match (c, quotes) {
+            ('\\', Quotes::None | Quotes::Double) => {
+                // The escaped character, unless it opens a tag.
+                if let Some(next) = rest.chars().next().filter(|_| tag_close(rest).is_none()) {
+                    out.push(next);
+                    rest = &rest[next.len_utf8()..];
+                }
+            }
+            ('\'', Quotes::None) => quotes = Quotes::Single,
+            ('\'', Quotes::Single) => quotes = Quotes::None,
+            ('"', Quotes::None) => quotes = Quotes::Double,
+            ('"', Quotes::Double) => quotes = Quotes::None,
+            _ => {}
+        }
+    }
+    out
+}
+
+/// The closing delimiter of the tag `source` starts with, if it starts with one.
+fn tag_close(source: &str) -> Option<&'static str> {
+    [("{{", "}}"), ("{%", "%}"), ("{#", "#}")]
+        .into_iter()
+        .find(|(open, _)| source.starts_with(open))
+        .map(|(_, close)| close)
+}
+
+/// If `source` starts with `{% raw %}`, the length up to and including the
+/// matching `{% endraw %}` (or all of it, if that is missing).
+fn raw_block_end(source: &str) -> Option<usize> {
+    let is_tag = |tag: &str, name: &str| {
+        tag.strip_prefix("{%")
+            .and_then(|tag| tag.strip_suffix("%}"))
+            .is_some_and(|inner| inner.trim_matches(['-', '+']).trim() == name)
+    };
+    let open_end = source.starts_with("{%").then(|| source.find("%}"))??  + 2;
+    if !is_tag(&source[..open_end], "raw") {
+        return None;
+    }
+    let mut from = open_end;
+    while let Some(start) = source[from..].find("{%").map(|i| from + i) {
+        let Some(end) = source[start..].find("%}").map(|i| start + i + 2) else { break };
+        if is_tag(&source[start..end], "endraw") {
+            return Some(end);
+        }
+        from = end;
+    }
+    Some(source.len())
+}
+
+/// `{{ expr }}` rewritten as `{{ (expr) | filter }}`, keeping whitespace control.
+fn through_filter(tag: &str, filter: &str) -> String {
+    let Some(inner) = tag.strip_prefix("{{").and_then(|tag| tag.strip_suffix("}}")) else {
+        return tag.to_string();
+    };
+    let (open, inner) = match inner.strip_prefix(['-', '+']) {
+        Some(rest) => (&inner[..1], rest),
+        None => ("", inner),
+    };
+    let (inner, close) = match inner.strip_suffix(['-', '+']) {
+        Some(rest) => (rest, &inner[rest.len()..]),
+        None => (inner, ""),
+    };
+    format!("{{{{{} ({}) | {} {}}}}}", open, inner.trim(), filter, close)
+}
+
+/// A multi-valued argument: iterates as a list but prints as the raw string.
+#[derive(Debug)]
+struct MultiValue {
+    raw: String,
+    items: Vec<Value>,
+}
+
+impl MultiValue {
+    fn new(raw: &str, delimiter: &str) -> Self {
+        let items = raw
+            .split(delimiter)
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(Value::from)
+            .collect();
+        MultiValue {
+            raw: raw.to_string(),
+            items,
+        }
+    }
+}
+
+impl Object for MultiValue {
+    fn repr(self: &Arc<Self>) -> ObjectRepr {
+        ObjectRepr::Seq
+    }
+
+    fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+        self.items.get(key.as_usize()?).cloned()
+    }
+
+    fn enumerate(self: &Arc<Self>) -> Enumerator {
+        Enumerator::Seq(self.items.len())
+    }
+
+    fn render(self: &Arc<Self>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
@@ -0,0 +1,55 @@
+use semver::{Version, VersionReq};
+
+/// Version of the running scaf binary.
+pub const SCAF_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub fn parse_version(version: &str) -> anyhow::Result<Version> {
+    Version::parse(version.trim())
+        .map_err(|err| anyhow::anyhow!("`{}` is not a valid semver version: {}", version, err))
+}
+
+/// Parse a version requirement. A bare version such as `1.2.0` pins that exact
+/// version; anything else (`^1.2`, `~1.2.3`, `>=1, <2`, `*`) is a semver range.
+/// An empty requirement or `latest` accepts any version.
+pub fn parse_requirement(requirement: &str) -> anyhow::Result<VersionReq> {
+    let requirement = requirement.trim();
+    if requirement.is_empty() || requirement == "latest" {
+        return Ok(VersionReq::STAR);
+    }
+    if let Ok(version) = Version::parse(requirement) {
+        return Ok(VersionReq::parse(&format!("={}", version))?);
+    }
+    VersionReq::parse(requirement)
+        .map_err(|err| anyhow::anyhow!("`{}` is not a valid version requirement: {}", requirement, err))
+}
+
+/// Whether a git ref should be resolved against the repository's tags rather
+/// than used as-is: it starts with a range operator (`^`, `~`, `=`, `<`, `>`, `*`).
+pub fn is_requirement(reference: &str) -> bool {
+    reference.starts_with(['^', '~', '=', '<', '>', '*'])
+}
+
+/// The highest candidate matching `requirement`, returned as given. Candidates may
+/// carry a `v` prefix (as git tags often do); those that are not versions are ignored.
+pub fn highest_match<'a>(requirement: &VersionReq, candidates: impl IntoIterator<Item = &'a str>)
+    -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .filter_map(|candidate| Some((Version::parse(candidate.trim_start_matches('v')).ok()?, candidate)))
+        .filter(|(version, _)| requirement.matches(version))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Fail if the template needs a newer scaf than the one running.
+pub fn check_min_scaf_version(template: &str, min: &str) -> anyhow::Result<()> {
+    let min = parse_version(min)?;
+    let running = parse_version(SCAF_VERSION)?;
+    if running < min {
+        anyhow::bail!(
+            "Template {} requires scaf {} or newer, but this is scaf {}; please upgrade",
+            template, min, running
+        );
+    }
+    Ok(())
+}
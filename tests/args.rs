@@ -1,3 +1,4 @@
+use scaf::answers::Answers;
 use scaf::args::ArgValue;
 use scaf::model::{
     args::{TemplateArg, ArgType},
@@ -19,6 +20,8 @@ async fn test_string_argument() {
         depends_on: None,
         multiple: false,
         delimiter: None,
+        secret: false,
+        conditions: None,
     };
 
     assert!(arg.validate_value("test").is_ok());
@@ -42,6 +45,8 @@ async fn test_number_argument() {
         depends_on: None,
         multiple: false,
         delimiter: None,
+        secret: false,
+        conditions: None,
     };
 
     assert!(arg.validate_value("42").is_ok());
@@ -76,6 +81,8 @@ async fn test_enum_argument() {
         depends_on: None,
         multiple: false,
         delimiter: None,
+        secret: false,
+        conditions: None,
     };
 
     assert!(arg.validate_value("option1").is_ok());
@@ -110,6 +117,8 @@ async fn test_multiple_argument() {
         depends_on: None,
         multiple: true,
         delimiter: Some(",".to_string()),
+        secret: false,
+        conditions: None,
     };
 
     assert!(arg.validate_value("option1,option2").is_ok());
@@ -133,6 +142,8 @@ async fn test_email_argument() {
         depends_on: None,
         multiple: false,
         delimiter: None,
+        secret: false,
+        conditions: None,
     };
 
     assert!(arg.validate_value("test@example.com").is_ok());
@@ -155,6 +166,8 @@ async fn test_url_argument() {
         depends_on: None,
         multiple: false,
         delimiter: None,
+        secret: false,
+        conditions: None,
     };
 
     assert!(arg.validate_value("https://example.com").is_ok());
@@ -178,9 +191,67 @@ async fn test_path_argument() {
         depends_on: None,
         multiple: false,
         delimiter: None,
+        secret: false,
+        conditions: None,
     };
 
     assert!(arg.validate_value("./test").is_ok());
     assert!(arg.validate_value("/test").is_ok());
     assert!(arg.validate_value("../test").is_err());
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_resolve_answer_from_file() {
+    let arg = TemplateArg {
+        name: "Features".to_string(),
+        key: "features".to_string(),
+        description: None,
+        long_description: None,
+        arg_type: ArgType::Enum,
+        default: "a".to_string(),
+        values: Some(vec![
+            ArgValue {
+                value: "a".to_string(),
+                description: "A".to_string(),
+                details: None,
+            },
+            ArgValue {
+                value: "b".to_string(),
+                description: "B".to_string(),
+                details: None,
+            },
+        ]),
+        required: true,
+        pattern: None,
+        group: None,
+        depends_on: None,
+        multiple: true,
+        delimiter: Some("|".to_string()),
+        secret: false,
+        conditions: None,
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("answers.yaml");
+    std::fs::write(&path, "features:\n  - a\n  - b\n").unwrap();
+    let answers = Answers::from_file(&path, false).unwrap();
+    assert_eq!(arg.resolve_answer(&answers).unwrap(), Some("a|b".to_string()));
+
+    let path = dir.path().join("answers.json");
+    std::fs::write(&path, r#"{"features": ["a", "c"]}"#).unwrap();
+    let answers = Answers::from_file(&path, false).unwrap();
+    assert!(arg.resolve_answer(&answers).is_err());
+
+    // Interactive runs without an answer fall through to the prompt
+    assert_eq!(arg.resolve_answer(&Answers::new(false)).unwrap(), None);
+    assert_eq!(arg.resolve_answer(&Answers::new(true)).unwrap(), Some("a".to_string()));
+}
+
+#[tokio::test]
+async fn test_answer_pairs() {
+    let mut answers = Answers::new(true);
+    assert!(answers.insert_pair("name=a=b").is_ok());
+    assert_eq!(answers.get("name").unwrap(), "a=b");
+    assert!(answers.insert_pair("no-separator").is_err());
+    assert!(answers.insert_pair("=value").is_err());
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use scaf::context::ExecutionContext;
+use scaf::config::Config;
+use scaf::loader::{load_template, TemplateSource};
+use tempfile::tempdir;
+use common::cached_in;
+
+const TEMPLATE: &str = r#"{
+    "name": "Bundle", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+    "tags": [], "extends": [],
+    "args": [{"name": "Name", "key": "name", "type": "string", "default": "demo"}],
+    "steps": [
+        {"id": "src", "description": "", "type": "copy", "source": "src", "path": "{{ name }}/src"},
+        {"id": "icon", "description": "", "type": "copy", "source": "favicon.ico", "path": "{{ name }}/public/favicon.ico"},
+        {"id": "docs", "description": "", "type": "copy", "source": "docs/*.md", "path": "{{ name }}/docs",
+         "template_engine": "none"}
+    ]
+}"#;
+
+const ICON: &[u8] = &[0, 1, 2, 255, 254, b'{', b'{', 0];
+
+fn write_bundle(root: &Path) {
+    std::fs::create_dir_all(root.join("files/src/bin")).unwrap();
+    std::fs::create_dir_all(root.join("files/docs")).unwrap();
+    std::fs::write(root.join("template.json"), TEMPLATE).unwrap();
+    std::fs::write(root.join("files/src/main.rs"), "// {{ name }}\n").unwrap();
+    std::fs::write(root.join("files/src/bin/{{ name }}.rs"), "fn main() {}\n").unwrap();
+    std::fs::write(root.join("files/favicon.ico"), ICON).unwrap();
+    std::fs::write(root.join("files/docs/guide.md"), "{{ not_rendered }}").unwrap();
+    std::fs::write(root.join("files/docs/notes.txt"), "skipped").unwrap();
+}
+
+async fn scaffold(source: TemplateSource) -> tempfile::TempDir {
+    let cache = tempdir().unwrap();
+    let template = load_template(&source, &cached_in(cache.path())).await.unwrap();
+    let dest = tempdir().unwrap();
+    let ctx = ExecutionContext::new(dest.path()).with_template(&template).strict(true);
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), "demo".to_string());
+    template.execute(&args, &ctx).await.unwrap();
+    dest
+}
+
+fn assert_scaffolded(dest: &Path) {
+    assert_eq!(std::fs::read_to_string(dest.join("demo/src/main.rs")).unwrap(), "// demo\n");
+    assert!(dest.join("demo/src/bin/demo.rs").exists());
+    assert_eq!(std::fs::read(dest.join("demo/public/favicon.ico")).unwrap(), ICON);
+    assert_eq!(std::fs::read_to_string(dest.join("demo/docs/guide.md")).unwrap(), "{{ not_rendered }}");
+    assert!(!dest.join("demo/docs/notes.txt").exists());
+}
+
+#[tokio::test]
+async fn test_directory_bundle() {
+    let bundle = tempdir().unwrap();
+    write_bundle(bundle.path());
+    let dest = scaffold(TemplateSource::File(bundle.path().to_path_buf())).await;
+    assert_scaffolded(dest.path());
+}
+
+#[tokio::test]
+async fn test_tar_gz_bundle() {
+    let bundle = tempdir().unwrap();
+    write_bundle(&bundle.path().join("my-template"));
+
+    let archive = bundle.path().join("my-template.tar.gz");
+    let encoder = flate2::write::GzEncoder::new(std::fs::File::create(&archive).unwrap(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    builder.append_dir_all("my-template", bundle.path().join("my-template")).unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let dest = scaffold(TemplateSource::File(archive)).await;
+    assert_scaffolded(dest.path());
+}
+
+#[tokio::test]
+async fn test_zip_bundle() {
+    let bundle = tempdir().unwrap();
+    let archive = bundle.path().join("my-template.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    let files: [(&str, &[u8]); 6] = [
+        ("template.json", TEMPLATE.as_bytes()),
+        ("files/src/main.rs", b"// {{ name }}\n"),
+        ("files/src/bin/{{ name }}.rs", b"fn main() {}\n"),
+        ("files/favicon.ico", ICON),
+        ("files/docs/guide.md", b"{{ not_rendered }}"),
+        ("files/docs/notes.txt", b"skipped"),
+    ];
+    for (name, content) in files {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let dest = scaffold(TemplateSource::File(archive)).await;
+    assert_scaffolded(dest.path());
+}
+
+#[tokio::test]
+async fn test_copy_without_bundle() {
+    let dir = tempdir().unwrap();
+    std::fs::write(dir.path().join("template.json"), TEMPLATE).unwrap();
+    let source = TemplateSource::File(dir.path().join("template.json"));
+    let template = load_template(&source, &Config::default()).await.unwrap();
+    let ctx = ExecutionContext::new(dir.path().join("out")).with_template(&template);
+
+    let err = template.execute(&HashMap::new(), &ctx).await.unwrap_err();
+    assert_eq!(err.to_string(), "Step src: copy steps need a template bundle with a files/ directory");
+}
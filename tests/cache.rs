@@ -0,0 +1,77 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+use reqwest::Url;
+use scaf::cache::HttpCache;
+use common::{serve, Request, Response};
+
+const ETAG: &str = "\"v1\"";
+
+/// Serve `body` with an ETag, answering 304 to requests that already have it.
+fn mock_server(body: &'static str) -> (Url, Arc<Mutex<Vec<Request>>>) {
+    let (base, requests) = serve(move |request| {
+        if request.header("if-none-match") == Some(ETAG) {
+            Response::status("304 Not Modified", "").header("ETag", ETAG)
+        } else {
+            Response::ok(body).header("ETag", ETAG)
+        }
+    });
+    (Url::parse(&format!("{}/template.json", base)).unwrap(), requests)
+}
+
+/// The `If-None-Match` header of every request.
+fn if_none_match(requests: &Mutex<Vec<Request>>) -> Vec<Option<String>> {
+    requests.lock().unwrap().iter().map(|r| r.header("if-none-match").map(str::to_string)).collect()
+}
+
+#[tokio::test]
+async fn test_revalidates_with_etag() {
+    let (url, requests) = mock_server(r#"{"name": "cached"}"#);
+    let dir = tempfile::tempdir().unwrap();
+    let cache = HttpCache::new(dir.path(), false);
+
+    assert_eq!(cache.get(&url).await.unwrap(), br#"{"name": "cached"}"#);
+    assert_eq!(cache.get(&url).await.unwrap(), br#"{"name": "cached"}"#);
+    assert_eq!(if_none_match(&requests), vec![None, Some(ETAG.to_string())]);
+
+    let entries = cache.entries().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].url, url.to_string());
+    assert_eq!(entries[0].etag.as_deref(), Some(ETAG));
+}
+
+#[tokio::test]
+async fn test_offline_uses_cache_and_fails_when_missing() {
+    let (url, requests) = mock_server("hello");
+    let dir = tempfile::tempdir().unwrap();
+    let missing = url.join("missing.json").unwrap();
+
+    let err = HttpCache::new(dir.path(), true).get(&url).await.unwrap_err().to_string();
+    assert!(err.contains("is not in the cache and --offline is set"), "{}", err);
+
+    HttpCache::new(dir.path(), false).get(&url).await.unwrap();
+    let offline = HttpCache::new(dir.path(), true);
+    assert_eq!(offline.get(&url).await.unwrap(), b"hello");
+    assert!(offline.get(&missing).await.is_err());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_server_errors_fall_back_to_cache() {
+    let failing = std::sync::atomic::AtomicBool::new(false);
+    let (base, _) = serve(move |_| {
+        if failing.swap(true, std::sync::atomic::Ordering::SeqCst) {
+            Response::status("503 Service Unavailable", "down")
+        } else {
+            Response::ok("hello")
+        }
+    });
+    let url = Url::parse(&format!("{}/template.json", base)).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let cache = HttpCache::new(dir.path(), false);
+
+    assert_eq!(cache.get(&url).await.unwrap(), b"hello");
+    assert_eq!(cache.get(&url).await.unwrap(), b"hello");
+    let err = cache.get(&url.join("other.json").unwrap()).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("503"), "{:#}", err);
+}
@@ -0,0 +1,109 @@
+//! Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use scaf::config::Config;
+
+/// A request received by [`serve`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    /// Path and query, e.g. `/api/v1/template?page=1`.
+    pub target: String,
+    /// Header names are lowercased.
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// What [`serve`] answers a request with.
+#[derive(Debug, Clone)]
+pub struct Response {
+    /// Status line after `HTTP/1.1`, e.g. `200 OK`.
+    pub status: &'static str,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn ok(body: impl Into<String>) -> Self {
+        Response::status("200 OK", body)
+    }
+
+    pub fn status(status: &'static str, body: impl Into<String>) -> Self {
+        Response { status, headers: Vec::new(), body: body.into() }
+    }
+
+    pub fn not_found() -> Self {
+        Response::status("404 Not Found", "{}")
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Serve HTTP on a local port, answering each request with `respond`. Returns
+/// the base URL and every request received so far.
+pub fn serve<F>(respond: F) -> (String, Arc<Mutex<Vec<Request>>>)
+where
+    F: Fn(&Request) -> Response + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let target = line.split_whitespace().nth(1).unwrap_or_default().to_string();
+            let mut headers = Vec::new();
+            line.clear();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+                }
+                line.clear();
+            }
+            let request = Request { target, headers };
+            let response = respond(&request);
+            seen.lock().unwrap().push(request);
+
+            let mut head = format!("HTTP/1.1 {}\r\n", response.status);
+            for (name, value) in &response.headers {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            write!(stream, "{}Content-Length: {}\r\nConnection: close\r\n\r\n{}", head, response.body.len(), response.body)
+                .unwrap();
+        }
+    });
+    (base, requests)
+}
+
+/// Serve `body` for every request.
+pub fn serve_body(body: &str) -> String {
+    let body = body.to_string();
+    serve(move |_| Response::ok(body.clone())).0
+}
+
+/// The default config, caching under `dir` instead of the user's cache directory.
+pub fn cached_in(dir: &Path) -> Config {
+    Config { cache_dir: Some(dir.to_path_buf()), ..Config::default() }
+}
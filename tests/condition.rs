@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use scaf::args::TemplateArg;
+use scaf::condition::{ArgDelimiters, ConditionOperator, Conditions};
+
+fn args(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+fn parse(json: &str) -> Conditions {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn test_nested_groups_and_not() {
+    let flat = parse(r#"{"conditions": [{"field": "database", "operator": "equals", "value": "mongodb"}]}"#);
+    assert!(flat.evaluate(&args(&[("database", "mongodb")]), &ArgDelimiters::default()));
+    let camel = parse(r#"{"conditions": [{"field": "database", "operator": "notEquals", "value": "none"}]}"#);
+    assert!(camel.evaluate(&args(&[("database", "mongodb")]), &ArgDelimiters::default()));
+
+    let nested = parse(r#"{
+        "operator": "or",
+        "conditions": [
+            {"field": "ci", "operator": "equals", "value": "github"},
+            {"operator": "and", "conditions": [
+                {"field": "docker", "operator": "equals", "value": "true"},
+                {"not": {"field": "database", "operator": "equals", "value": "none"}}
+            ]}
+        ]
+    }"#);
+    assert!(nested.evaluate(&args(&[("ci", "github")]), &ArgDelimiters::default()));
+    assert!(nested.evaluate(&args(&[("docker", "true"), ("database", "postgres")]), &ArgDelimiters::default()));
+    assert!(!nested.evaluate(&args(&[("docker", "true"), ("database", "none")]), &ArgDelimiters::default()));
+    assert_eq!(nested.fields(), vec!["ci", "docker", "database"]);
+}
+
+#[test]
+fn test_numeric_regex_semver_and_presence_operators() {
+    let values = args(&[("port", "8080"), ("node", "18.10.0"), ("name", "my-app"), ("notes", " ")]);
+    let check = |field: &str, operator: &str, value: &str| parse(&format!(
+        r#"{{"conditions": [{{"field": "{}", "operator": "{}", "value": "{}"}}]}}"#, field, operator, value
+    )).evaluate(&values, &ArgDelimiters::default());
+
+    assert!(check("port", "gt", "1024"));
+    assert!(check("port", "lte", "8080"));
+    assert!(!check("port", "lt", "80"));
+    assert!(!check("name", "gt", "1"));
+    assert!(check("node", "gte", "18.9.0"));
+    assert!(check("node", "satisfies", "^18.2"));
+    assert!(!check("node", "satisfies", ">=20"));
+    assert!(check("name", "matches", "^[a-z-]+$"));
+    assert!(!check("name", "matches", "^[A-Z]"));
+    assert!(check("name", "exists", ""));
+    assert!(!check("missing", "exists", ""));
+    assert!(check("missing", "empty", ""));
+    assert!(check("notes", "empty", ""));
+    assert!(!check("name", "empty", ""));
+}
+
+#[test]
+fn test_compact_string_form() {
+    let conditions: Conditions = serde_json::from_str(r#""database == 'postgres' && 'docker' in features""#).unwrap();
+    assert!(conditions.evaluate(&args(&[("database", "postgres"), ("features", "auth, docker")]), &ArgDelimiters::default()));
+    assert!(!conditions.evaluate(&args(&[("database", "postgres"), ("features", "auth")]), &ArgDelimiters::default()));
+    assert_eq!(conditions.leaves()[1].operator, ConditionOperator::In);
+
+    let conditions: Conditions =
+        "!(use_ci || port < 1024) and exists(name) and node satisfies '>=18' and 'x' not in tags".parse().unwrap();
+    let values = args(&[("use_ci", "false"), ("port", "8080"), ("name", "app"), ("node", "18.0.0"), ("tags", "a,b")]);
+    assert!(conditions.evaluate(&values, &ArgDelimiters::default()));
+    let mut ci = values.clone();
+    ci.insert("use_ci".to_string(), "true".to_string());
+    assert!(!conditions.evaluate(&ci, &ArgDelimiters::default()));
+
+    for broken in ["database ==", "database == 'x' &&", "(a == 'b'", "'x' features", "name == 'unterminated"] {
+        assert!(broken.parse::<Conditions>().is_err(), "{}", broken);
+    }
+    assert!(serde_json::from_str::<Conditions>(r#""database = 'x'""#).is_err());
+}
+
+#[test]
+fn test_multi_value_operators_use_the_arg_delimiter() {
+    let declared: Vec<TemplateArg> = serde_json::from_str(r#"[
+        {"name": "Features", "key": "features", "type": "string", "default": "", "multiple": true, "delimiter": "|"},
+        {"name": "Router", "key": "router", "type": "string", "default": ""}
+    ]"#).unwrap();
+    let delimiters = ArgDelimiters::for_args(&declared);
+    let values = args(&[("features", "react-router | docker|auth"), ("router", "react-router,v6")]);
+    let check = |expression: &str| expression.parse::<Conditions>().unwrap().evaluate(&values, &delimiters);
+
+    assert!(check("'docker' in features"));
+    assert!(!check("'react' in features"));
+    assert!(check("features contains 'react-router'"));
+    assert!(!check("features contains 'react'"));
+    assert!(check("features containsAll 'auth|docker'"));
+    assert!(!check("features containsAll 'auth|ci'"));
+    assert!(check("features containsAny 'ci|auth'"));
+    assert!(!check("features containsAny 'ci,auth'"));
+    assert!(!check("'react-router' in router"));
+    assert!(check("'react-router,v6' in router"));
+    assert!(check("router contains 'react'"));
+
+    let json = parse(r#"{"conditions": [{"field": "features", "operator": "containsAll", "value": "docker|auth"}]}"#);
+    assert!(json.evaluate(&values, &delimiters));
+    assert!(!check("empty(features)"));
+}
@@ -0,0 +1,104 @@
+use scaf::config::Config;
+use scaf::loader::{load_template, TemplateSource};
+use tempfile::tempdir;
+
+fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+#[test]
+fn test_layers_override_in_order() {
+    let dir = tempdir().unwrap();
+    let system = dir.path().join("system.toml");
+    let user = dir.path().join("user.toml");
+    let project = dir.path().join(".scafrc");
+    std::fs::write(&system, r#"
+        registry_url = "https://system.example.com"
+        dest = "/srv"
+        trusted = ["https://github.com/acme"]
+        [defaults]
+        author = "System"
+        license = "MIT"
+        [aliases]
+        svc = "company/service"
+    "#).unwrap();
+    std::fs::write(&user, r#"
+        [defaults]
+        author = "Jane"
+        [aliases]
+        web = "gh:acme/web"
+    "#).unwrap();
+    std::fs::write(&project, r#"
+        registry_url = "https://project.example.com/"
+        trusted = ["https://evil.example.com"]
+        [aliases]
+        local = "./templates/local.json"
+    "#).unwrap();
+
+    let paths = vec![system, user, project, dir.path().join("missing.toml")];
+    let config = Config::load_from(&paths, env(&[
+        ("SCAF_DEST", "/tmp/out"),
+        ("SCAF_DEFAULT_EMAIL", "jane@example.com"),
+        ("SCAF_TRUSTED", "https://templates.example.com, gh-mirror.example.com"),
+        ("HOME", "/home/jane"),
+    ])).unwrap();
+
+    // A project's .scafrc may neither switch registries nor trust origins.
+    assert_eq!(config.registry_url(), "https://system.example.com");
+    assert_eq!(config.dest.as_deref(), Some(std::path::Path::new("/tmp/out")));
+    assert_eq!(config.defaults["author"], "Jane");
+    assert_eq!(config.defaults["license"], "MIT");
+    assert_eq!(config.defaults["email"], "jane@example.com");
+    assert_eq!(config.aliases.len(), 3);
+    assert_eq!(config.trusted, vec![
+        "https://github.com/acme", "https://templates.example.com", "gh-mirror.example.com",
+    ]);
+}
+
+#[test]
+fn test_invalid_config_is_an_error() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "registry = 'typo'").unwrap();
+    let err = Config::load_from(&[path], Vec::new()).unwrap_err();
+    assert!(err.to_string().contains("Failed to parse config"));
+}
+
+#[test]
+fn test_aliases_and_registry_url() {
+    let config = Config::load_from(&[], env(&[
+        ("SCAF_REGISTRY_URL", "https://templates.example.com"),
+        ("SCAF_ALIAS_SVC", "acme/service"),
+        ("SCAF_ALIAS_LOCAL", "/opt/templates/local.json"),
+    ])).unwrap();
+
+    let source = TemplateSource::parse("svc", false, &config).unwrap();
+    assert_eq!(source.to_string(), "https://templates.example.com/api/v1/template/acme/service");
+    let source = TemplateSource::parse("react", false, &config).unwrap();
+    assert_eq!(source.to_string(), "https://templates.example.com/api/v1/template/scaf/react");
+    let source = TemplateSource::parse("local", false, &config).unwrap();
+    assert_eq!(source, TemplateSource::File("/opt/templates/local.json".into()));
+}
+
+#[tokio::test]
+async fn test_defaults_replace_template_defaults() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("template.json");
+    std::fs::write(&path, r#"{
+        "name": "T", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [], "steps": [],
+        "args": [
+            {"name": "Author", "key": "author", "type": "string", "default": "nobody"},
+            {"name": "Port", "key": "port", "type": "number", "default": "80"}
+        ]
+    }"#).unwrap();
+
+    let config = Config::load_from(&[], env(&[("SCAF_DEFAULT_AUTHOR", "Jane")])).unwrap();
+    let template = load_template(&TemplateSource::File(path.clone()), &config).await.unwrap();
+    assert_eq!(template.args[0].default, "Jane");
+    assert_eq!(template.args[1].default, "80");
+
+    let config = Config::load_from(&[], env(&[("SCAF_DEFAULT_PORT", "http")])).unwrap();
+    let err = load_template(&TemplateSource::File(path), &config).await.unwrap_err();
+    assert!(err.to_string().contains("Invalid configured default for `port`"));
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+use scaf::git::GitSource;
+use scaf::loader::{load_template, TemplateSource};
+use tempfile::tempdir;
+use common::cached_in;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=scaf", "-c", "user.email=scaf@example.com"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(status.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&status.stderr));
+}
+
+fn manifest(name: &str, version: &str) -> String {
+    format!(r#"{{
+        "name": "{}", "version": "{}", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [], "args": [], "steps": []
+    }}"#, name, version)
+}
+
+#[test]
+fn test_parse_git_references() {
+    let source = GitSource::parse("git+file:///srv/templates.git//rust/service#v1.2").unwrap().unwrap();
+    assert_eq!(source.url, "file:///srv/templates.git");
+    assert_eq!(source.subdir.as_deref(), Some("rust/service"));
+    assert_eq!(source.reference.as_deref(), Some("v1.2"));
+
+    let source = GitSource::parse("gh:acme/templates/rust@v2").unwrap().unwrap();
+    assert_eq!(source.url, "https://github.com/acme/templates.git");
+    assert_eq!(source.subdir.as_deref(), Some("rust"));
+    assert_eq!(source.reference.as_deref(), Some("v2"));
+
+    assert!(GitSource::parse("gh:acme").unwrap().is_err());
+    assert!(GitSource::parse("git+file:///srv/t.git//../etc").unwrap().is_err());
+    assert!(GitSource::parse("acme/templates").is_none());
+}
+
+#[tokio::test]
+async fn test_option_like_refs_are_refused() {
+    let marker = tempdir().unwrap();
+    let pwned = marker.path().join("pwned");
+    for reference in [
+        format!("git+file:///srv/t.git#--upload-pack=touch {};git-upload-pack", pwned.display()),
+        "git+--upload-pack=touch /tmp/x".to_string(),
+        "gh:acme/templates@-oProxyCommand=touch".to_string(),
+    ] {
+        let err = GitSource::parse(&reference).unwrap().unwrap_err();
+        assert!(err.to_string().contains("must not start with `-`"), "{}", err);
+    }
+
+    let source = GitSource {
+        url: "file:///srv/t.git".to_string(),
+        reference: Some(format!("--upload-pack=touch {};git-upload-pack", pwned.display())),
+        subdir: None,
+    };
+    let cache = tempdir().unwrap();
+    assert!(source.checkout(cache.path(), false).await.is_err());
+    assert!(!pwned.exists());
+}
+
+#[tokio::test]
+async fn test_load_template_from_git_repository() {
+    let cache = tempdir().unwrap();
+
+    let work = tempdir().unwrap();
+    let repo = work.path().join("repo");
+    std::fs::create_dir_all(repo.join("service")).unwrap();
+    git(&repo, &["init", "--quiet"]);
+    std::fs::write(repo.join("template.json"), manifest("Root", "1.0.0")).unwrap();
+    std::fs::write(repo.join("service/template.json"), manifest("Service", "1.0.0")).unwrap();
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "--quiet", "-m", "v1"]);
+    git(&repo, &["tag", "v1"]);
+    std::fs::write(repo.join("template.json"), manifest("Root", "2.0.0")).unwrap();
+    git(&repo, &["commit", "--quiet", "-am", "v2"]);
+
+    let bare = work.path().join("templates.git");
+    git(work.path(), &["clone", "--quiet", "--bare", repo.to_str().unwrap(), bare.to_str().unwrap()]);
+    let url = format!("git+file://{}", bare.display());
+    let config = cached_in(cache.path());
+    let load = |reference: String| {
+        let config = config.clone();
+        async move {
+            let source = TemplateSource::parse(&reference, false, &config)?;
+            assert!(matches!(source, TemplateSource::Git(_)), "{}", reference);
+            load_template(&source, &config).await
+        }
+    };
+
+    let template = load(format!("{}#v1", url)).await.unwrap();
+    assert_eq!((template.name.as_str(), template.version.as_str()), ("Root", "1.0.0"));
+
+    let template = load(url.clone()).await.unwrap();
+    assert_eq!(template.version, "2.0.0");
+
+    git(&repo, &["tag", "v2.0.0"]);
+    git(&work.path().join("templates.git"), &["fetch", "--quiet", "--tags", repo.to_str().unwrap()]);
+    let template = load(format!("{}#^2", url)).await.unwrap();
+    assert_eq!(template.version, "2.0.0");
+    let err = load(format!("{}#^3", url)).await.unwrap_err();
+    assert!(err.to_string().contains("No tag of"), "{}", err);
+
+    let template = load(format!("{}//service#v1", url)).await.unwrap();
+    assert_eq!(template.name, "Service");
+    assert!(cache.path().join("git").is_dir());
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+use scaf::config::Config;
+use scaf::loader::{load_template, TemplateSource};
+use tempfile::tempdir;
+
+fn write_template(dir: &Path, file: &str, name: &str, version: &str, extends: &str, args: &str, steps: &str) {
+    let json = format!(r#"{{
+        "name": "{}", "version": "{}", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [{}], "args": [{}], "steps": [{}]
+    }}"#, name, version, extends, args, steps);
+    std::fs::write(dir.join(file), json).unwrap();
+}
+
+#[tokio::test]
+async fn test_extends_merges_parent_first() {
+    let dir = tempdir().unwrap();
+    write_template(dir.path(), "base.json", "Base", "1.0.0", "",
+        r#"{"name": "Owner", "key": "owner", "type": "string", "default": "nobody"},
+           {"name": "Name", "key": "name", "type": "string", "default": "base"}"#,
+        r#"{"id": "readme", "description": "", "type": "file", "path": "README.md", "content": "base"},
+           {"id": "ci", "description": "", "type": "file", "path": "ci.yml", "content": "base"}"#);
+    write_template(dir.path(), "child.json", "Child", "2.0.0",
+        r#"{"template_id": "./base.json", "version": "1.0.0", "args": [{"name": "owner", "value": "platform"}]}"#,
+        r#"{"name": "Name", "key": "name", "type": "string", "default": "child"}"#,
+        r#"{"id": "readme", "description": "", "type": "file", "path": "README.md", "content": "child"},
+           {"id": "main", "description": "", "type": "file", "path": "main.rs", "content": ""}"#);
+
+    let source = TemplateSource::parse(dir.path().join("child.json").to_str().unwrap(), true, &Config::default()).unwrap();
+    let template = load_template(&source, &Config::default()).await.unwrap();
+
+    let keys: Vec<&str> = template.args.iter().map(|a| a.key.as_str()).collect();
+    assert_eq!(keys, vec!["owner", "name"]);
+    assert_eq!(template.args[0].default, "platform");
+    assert_eq!(template.args[1].default, "child");
+
+    let ids: Vec<&str> = template.steps.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec!["ci", "readme", "main"]);
+    assert_eq!(template.steps[1].content.as_deref(), Some("child"));
+
+    // Configured defaults apply to each template before the values its child extends it with.
+    let config = Config::load_from(&[], vec![
+        ("SCAF_DEFAULT_OWNER".to_string(), "configured".to_string()),
+        ("SCAF_DEFAULT_NAME".to_string(), "configured".to_string()),
+    ]).unwrap();
+    let template = load_template(&source, &config).await.unwrap();
+    assert_eq!(template.args[0].default, "platform");
+    assert_eq!(template.args[1].default, "configured");
+}
+
+#[tokio::test]
+async fn test_extends_errors() {
+    let dir = tempdir().unwrap();
+    write_template(dir.path(), "a.json", "A", "1.0.0",
+        r#"{"template_id": "./b.json", "version": "*", "args": []}"#, "", "");
+    write_template(dir.path(), "b.json", "B", "1.0.0",
+        r#"{"template_id": "./a.json", "version": "*", "args": []}"#, "", "");
+    let source = TemplateSource::parse(dir.path().join("a.json").to_str().unwrap(), true, &Config::default()).unwrap();
+    let err = load_template(&source, &Config::default()).await.unwrap_err().to_string();
+    assert!(err.starts_with("Template extends cycle:"), "{}", err);
+
+    write_template(dir.path(), "base.json", "Base", "1.1.0", "", "", "");
+    write_template(dir.path(), "child.json", "Child", "1.0.0",
+        r#"{"template_id": "./base.json", "version": "1.0.0", "args": []}"#, "", "");
+    let source = TemplateSource::parse(dir.path().join("child.json").to_str().unwrap(), true, &Config::default()).unwrap();
+    let err = load_template(&source, &Config::default()).await.unwrap_err().to_string();
+    assert_eq!(err, "Template Child extends ./base.json@1.0.0 but found version 1.1.0");
+
+    write_template(dir.path(), "child.json", "Child", "1.0.0",
+        r#"{"template_id": "./base.json", "version": "1.1.0", "args": [{"name": "nope", "value": "x"}]}"#, "", "");
+    assert!(load_template(&source, &Config::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_source_join() {
+    let config = Config::default();
+    let file = TemplateSource::File("templates/child.json".into());
+    assert_eq!(file.join("./base.json", &config).unwrap(), TemplateSource::File("templates/./base.json".into()));
+
+    let url = TemplateSource::parse("https://example.com/t/child.json", false, &config).unwrap();
+    assert_eq!(url.join("../base.json", &config).unwrap().to_string(), "https://example.com/base.json");
+    assert_eq!(url.join("user/base", &config).unwrap().to_string(), "https://scaf.pages.dev/api/v1/template/user/base");
+}
+
+#[tokio::test]
+async fn test_versions_are_semver() {
+    let dir = tempdir().unwrap();
+    let config = Config::default();
+    write_template(dir.path(), "base.json", "Base", "1.4.0", "", "", "");
+    write_template(dir.path(), "range.json", "Range", "1.0.0",
+        r#"{"template_id": "./base.json", "version": "^1.2", "args": []}"#, "", "");
+    write_template(dir.path(), "old.json", "Old", "1.0.0",
+        r#"{"template_id": "./base.json", "version": "~1.3", "args": []}"#, "", "");
+    write_template(dir.path(), "loose.json", "Loose", "one", "", "", "");
+
+    assert!(load_template(&TemplateSource::File(dir.path().join("range.json")), &config).await.is_ok());
+    let err = load_template(&TemplateSource::File(dir.path().join("old.json")), &config).await.unwrap_err();
+    assert!(err.to_string().contains("extends ./base.json@~1.3 but found version 1.4.0"), "{}", err);
+    let err = load_template(&TemplateSource::File(dir.path().join("loose.json")), &config).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("not a valid semver version"), "{:#}", err);
+
+    std::fs::write(dir.path().join("future.json"), r#"{
+        "name": "Future", "version": "1.0.0", "min_scaf_version": "99.0.0", "description": "", "author": "",
+        "language": "rust", "tags": [], "extends": [], "args": [], "steps": []
+    }"#).unwrap();
+    let err = load_template(&TemplateSource::File(dir.path().join("future.json")), &config).await.unwrap_err();
+    assert!(err.to_string().contains("requires scaf 99.0.0 or newer"), "{}", err);
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use scaf::context::ExecutionContext;
+use scaf::manifest::{ProjectManifest, BASE_DIR, MANIFEST_FILE};
+use scaf::Template;
+use tempfile::tempdir;
+
+const TEMPLATE: &str = r##"{
+    "name": "Service", "version": "1.2.0", "description": "", "author": "", "language": "rust",
+    "tags": [], "extends": [],
+    "args": [
+        {"name": "Name", "key": "name", "type": "string", "default": "demo"},
+        {"name": "Token", "key": "token", "type": "string", "default": "", "secret": true}
+    ],
+    "steps": [
+        {"id": "dir", "description": "", "type": "directory", "path": "{{ name }}"},
+        {"id": "readme", "description": "", "type": "file", "path": "{{ name }}/README.md", "content": "# {{ name }}\n"},
+        {"id": "env", "description": "", "type": "modify", "path": "{{ name }}/.env", "content": "TOKEN={{ token }}",
+         "modification_type": "append"},
+        {"id": "docker", "description": "", "type": "file", "path": "Dockerfile", "content": "",
+         "conditions": {"conditions": [{"field": "name", "operator": "equals", "value": "other"}]}}
+    ]
+}"##;
+
+#[tokio::test]
+async fn test_manifest_records_run() {
+    let template: Template = serde_json::from_str(TEMPLATE).unwrap();
+    let dest = tempdir().unwrap();
+    let ctx = ExecutionContext::new(dest.path()).with_template(&template);
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), "demo".to_string());
+    args.insert("token".to_string(), "s3cret".to_string());
+
+    let report = template.execute(&args, &ctx).await.unwrap();
+    let manifest = ProjectManifest::new(&template, "service.json", &args, &report, &ctx).unwrap();
+    manifest.save(dest.path(), dest.path()).unwrap();
+
+    let written = std::fs::read_to_string(dest.path().join(MANIFEST_FILE)).unwrap();
+    assert!(!written.contains("s3cret"));
+
+    let loaded = ProjectManifest::load(dest.path()).unwrap();
+    assert_eq!(loaded, manifest);
+    assert_eq!(loaded.source, "service.json");
+    assert_eq!((loaded.name.as_str(), loaded.version.as_str()), ("Service", "1.2.0"));
+    assert_eq!(loaded.template_hash, ProjectManifest::template_hash(&template).unwrap());
+    assert_eq!(loaded.answers.keys().collect::<Vec<_>>(), vec!["name"]);
+    assert_eq!(loaded.executed_steps, vec!["dir", "readme", "env"]);
+    assert_eq!(loaded.skipped_steps, vec!["docker"]);
+    assert_eq!(loaded.files.keys().collect::<Vec<_>>(), vec!["demo/.env", "demo/README.md"]);
+    assert_eq!(
+        loaded.files["demo/README.md"],
+        scaf::bundle::sha256_hex(b"# demo\n")
+    );
+    assert_eq!(std::fs::read_to_string(dest.path().join(BASE_DIR).join("demo/README.md")).unwrap(), "# demo\n");
+}
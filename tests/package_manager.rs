@@ -0,0 +1,38 @@
+use scaf::model::package_manager::PackageManager;
+use tempfile::tempdir;
+
+fn argv(manager: PackageManager, packages: &[&str], dev: bool) -> String {
+    let packages: Vec<String> = packages.iter().map(|p| p.to_string()).collect();
+    manager.install_argv(&packages, dev).join(" ")
+}
+
+#[tokio::test]
+async fn test_install_commands_and_version_specifiers() {
+    assert_eq!(argv(PackageManager::Npm, &["react@^18", "@types/react"], false), "npm install react@^18 @types/react");
+    assert_eq!(argv(PackageManager::Pnpm, &["@types/node@20"], true), "pnpm add --save-dev @types/node@20");
+    assert_eq!(argv(PackageManager::Yarn, &["eslint"], true), "yarn add --dev eslint");
+    assert_eq!(argv(PackageManager::Bun, &["zod@3.22.4"], false), "bun add zod@3.22.4");
+    assert_eq!(argv(PackageManager::Cargo, &["serde@1.0", "tokio"], true), "cargo add --dev serde@1.0 tokio");
+    assert_eq!(argv(PackageManager::Pip, &["requests@2.31.0", "flask@>=3", "rich>=13"], true),
+               "pip install requests==2.31.0 flask>=3 rich>=13");
+    assert_eq!(argv(PackageManager::Uv, &["pytest@~=8.0"], true), "uv add --dev pytest~=8.0");
+    assert_eq!(argv(PackageManager::Poetry, &["black@^24"], true), "poetry add --group dev black@^24");
+    assert_eq!(argv(PackageManager::Go, &["github.com/spf13/cobra@v1.8.0"], false),
+               "go get github.com/spf13/cobra@v1.8.0");
+    assert_eq!(argv(PackageManager::Go, &["golang.org/x/text@0.14.0", "rsc.io/quote@latest"], false),
+               "go get golang.org/x/text@v0.14.0 rsc.io/quote@latest");
+}
+
+#[tokio::test]
+async fn test_parse_and_detect() {
+    assert_eq!("PNPM".parse::<PackageManager>().unwrap(), PackageManager::Pnpm);
+    let err = "gradle".parse::<PackageManager>().unwrap_err().to_string();
+    assert!(err.starts_with("unknown package manager `gradle` (expected one of npm, yarn"), "{}", err);
+
+    let dir = tempdir().unwrap();
+    assert_eq!(PackageManager::detect(dir.path()), None);
+    std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+    assert_eq!(PackageManager::detect(dir.path()), Some(PackageManager::Npm));
+    std::fs::write(dir.path().join("yarn.lock"), "").unwrap();
+    assert_eq!(PackageManager::detect(dir.path()), Some(PackageManager::Yarn));
+}
@@ -0,0 +1,57 @@
+use scaf::config::Config;
+use scaf::model::Template;
+use scaf::picker::{candidates, local_templates, record_recent, recent_templates, Origin, RECENT_FILE, TEMPLATES_DIR};
+
+const TEMPLATE: &str = r#"{
+    "name": "Service", "version": "1.0.0", "description": "A web service", "author": "acme",
+    "language": "rust", "tags": ["api", "http"], "extends": [], "args": [], "steps": []
+}"#;
+
+#[test]
+fn test_local_templates_and_recent_history() {
+    let home = tempfile::tempdir().unwrap();
+    let dir = home.path().join(TEMPLATES_DIR);
+    std::fs::create_dir_all(dir.join("bundle")).unwrap();
+    std::fs::write(dir.join("service.json"), TEMPLATE).unwrap();
+    std::fs::write(dir.join("bundle").join("template.json"), TEMPLATE.replace("Service", "Bundle")).unwrap();
+    std::fs::write(dir.join("broken.json"), "{").unwrap();
+    std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+    let local = local_templates(&dir);
+    let names: Vec<&str> = local.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["Bundle", "Service"]);
+    assert_eq!(local[1].reference, dir.join("service.json").to_string_lossy());
+    assert_eq!(local[1].language.as_deref(), Some("Rust"));
+    assert_eq!(local[1].label(), format!("Service — A web service [Rust, api, http] (local: {})", local[1].reference));
+
+    let recent = home.path().join(RECENT_FILE);
+    let template: Template = serde_json::from_str(TEMPLATE).unwrap();
+    record_recent(&recent, "acme/service", &template).unwrap();
+    record_recent(&recent, "gh:acme/other", &template).unwrap();
+    record_recent(&recent, "acme/service", &template).unwrap();
+    let references: Vec<String> = recent_templates(&recent).into_iter().map(|c| c.reference).collect();
+    assert_eq!(references, vec!["acme/service", "gh:acme/other"]);
+}
+
+#[tokio::test]
+async fn test_candidates_merge_sources_without_registry() {
+    let home = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(home.path().join(TEMPLATES_DIR)).unwrap();
+    std::fs::write(home.path().join(TEMPLATES_DIR).join("service.json"), TEMPLATE).unwrap();
+    let template: Template = serde_json::from_str(TEMPLATE).unwrap();
+    record_recent(&home.path().join(RECENT_FILE), "svc", &template).unwrap();
+    let config = Config::load_from(&[], vec![
+        ("SCAF_REGISTRY_URL".to_string(), "http://127.0.0.1:9".to_string()),
+        ("SCAF_ALIAS_SVC".to_string(), "gh:acme/service".to_string()),
+        ("SCAF_ALIAS_WEB".to_string(), "gh:acme/web".to_string()),
+    ]).unwrap();
+
+    let found = candidates(&config, home.path()).await;
+    let summary: Vec<(&str, Origin)> = found.iter().map(|c| (c.reference.as_str(), c.origin)).collect();
+    let local = home.path().join(TEMPLATES_DIR).join("service.json");
+    assert_eq!(summary, vec![
+        ("svc", Origin::Recent),
+        ("web", Origin::Alias),
+        (local.to_str().unwrap(), Origin::Local),
+    ]);
+}
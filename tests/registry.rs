@@ -0,0 +1,109 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+use scaf::cache::HttpCache;
+use scaf::config::Config;
+use scaf::loader::{load_template, TemplateSource};
+use scaf::registry::{RegistryClient, SearchQuery};
+use common::{serve, Request, Response};
+
+/// Serve canned JSON bodies by path, recording each request's path and query.
+fn mock_registry(routes: Vec<(&'static str, String)>) -> (String, Arc<Mutex<Vec<Request>>>) {
+    serve(move |request| match routes.iter().find(|(route, _)| *route == request.path()) {
+        Some((_, body)) => Response::ok(body.clone()).header("Content-Type", "application/json"),
+        None => Response::not_found(),
+    })
+}
+
+const TEMPLATE: &str = r#"{
+    "name": "Service", "version": "2.0.0", "description": "A service", "author": "acme", "language": "rust",
+    "tags": ["api"], "extends": [], "args": [], "steps": []
+}"#;
+
+#[tokio::test]
+async fn test_search_sends_filters_and_parses_page() {
+    let (base, requests) = mock_registry(vec![("/api/v1/template", r#"{
+        "data": [
+            {"_id": "acme/service", "name": "Service", "version": "2.0.0", "language": "rust",
+             "tags": ["api", "http"], "createdBy": "acme", "status": "published"},
+            {"_id": "acme/web", "name": "Web", "version": "1.0.0", "language": "typescript", "tags": ["api"]}
+        ],
+        "pagination": {"total": 5, "page": 1, "limit": 2, "totalPages": 3}
+    }"#.to_string())]);
+    let cache = tempfile::tempdir().unwrap();
+    let client = RegistryClient::new(&base, HttpCache::new(cache.path(), false)).unwrap();
+
+    let page = client.search(&SearchQuery {
+        text: Some("web api".to_string()),
+        tags: vec!["api".to_string(), "http".to_string()],
+        language: Some("rust".to_string()),
+        page: 1,
+        limit: 2,
+    }).await.unwrap();
+
+    assert_eq!(page.data.len(), 1);
+    assert_eq!(page.data[0].id, "acme/service");
+    assert_eq!(page.data[0].author, "acme");
+    assert!(page.has_next());
+    assert_eq!(requests.lock().unwrap()[0].target, "/api/v1/template?search=web+api&page=1&limit=2");
+}
+
+#[tokio::test]
+async fn test_get_template() {
+    let (base, _) = mock_registry(vec![
+        ("/api/v1/template/acme/service", TEMPLATE.to_string()),
+        ("/api/v1/template/acme/missing", "null".to_string()),
+        ("/api/v1/template/scaf/react", TEMPLATE.to_string()),
+    ]);
+    let cache = tempfile::tempdir().unwrap();
+    let client = RegistryClient::new(&format!("{}/", base), HttpCache::new(cache.path(), false)).unwrap();
+
+    let template = client.get("acme/service").await.unwrap();
+    assert_eq!(template.name, "Service");
+    assert!(client.get("react").await.is_ok());
+
+    let err = client.get("acme/missing").await.unwrap_err();
+    assert_eq!(err.to_string(), "Template acme/missing was not found in the registry");
+    let err = client.get("acme/gone").await.unwrap_err();
+    assert!(format!("{:#}", err).contains("404"));
+    assert!(client.get("a/b/c").await.is_err());
+}
+
+#[test]
+fn test_registry_ids_resolve_against_configured_registry() {
+    let config = Config::load_from(&[], vec![("SCAF_REGISTRY_URL".to_string(), "http://localhost:9/base".to_string())]).unwrap();
+    let source = TemplateSource::parse("acme/service", false, &config).unwrap();
+    assert_eq!(source.to_string(), "http://localhost:9/base/api/v1/template/acme/service");
+}
+
+#[tokio::test]
+async fn test_pinned_registry_ids_check_the_served_version() {
+    let (base, requests) = mock_registry(vec![
+        ("/api/v1/template/acme/service", TEMPLATE.replace("2.0.0", "1.3.1")),
+        ("/api/v1/template/acme/missing", "null".to_string()),
+    ]);
+    let cache = tempfile::tempdir().unwrap();
+    let config = Config::load_from(&[], vec![
+        ("SCAF_REGISTRY_URL".to_string(), base),
+        ("SCAF_CACHE_DIR".to_string(), cache.path().display().to_string()),
+    ]).unwrap();
+
+    let source = TemplateSource::parse("acme/service@^1.2", false, &config).unwrap();
+    assert_eq!(source.to_string(), "acme/service@^1.2");
+    assert_eq!(load_template(&source, &config).await.unwrap().version, "1.3.1");
+
+    let source = TemplateSource::parse("acme/service@1.3.1", false, &config).unwrap();
+    assert_eq!(load_template(&source, &config).await.unwrap().version, "1.3.1");
+    assert_eq!(TemplateSource::from_recorded(&source.recorded(), &config).unwrap(), source);
+
+    let source = TemplateSource::parse("acme/service@1.2.0", false, &config).unwrap();
+    let err = load_template(&source, &config).await.unwrap_err().to_string();
+    assert_eq!(err, "No version of acme/service matches 1.2.0: the registry does not list versions and serves 1.3.1");
+
+    let source = TemplateSource::parse("acme/missing@1", false, &config).unwrap();
+    let err = load_template(&source, &config).await.unwrap_err().to_string();
+    assert_eq!(err, "Template acme/missing@1 was not found");
+
+    assert!(TemplateSource::parse("acme/service@not-a-version", false, &config).is_err());
+    assert!(requests.lock().unwrap().iter().all(|request| request.target.starts_with("/api/v1/template/acme/")));
+}
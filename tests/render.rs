@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use scaf::args::{ArgType, TemplateArg};
+use scaf::render::Renderer;
+
+fn arg(key: &str, arg_type: ArgType, multiple: bool, delimiter: Option<&str>) -> TemplateArg {
+    TemplateArg {
+        name: key.to_string(),
+        key: key.to_string(),
+        description: None,
+        long_description: None,
+        arg_type,
+        default: "".to_string(),
+        values: None,
+        required: false,
+        pattern: None,
+        group: None,
+        depends_on: None,
+        multiple,
+        delimiter: delimiter.map(str::to_string),
+        secret: false,
+        conditions: None,
+    }
+}
+
+fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+#[tokio::test]
+async fn test_render_placeholders_and_filters() {
+    let renderer = Renderer::default();
+    let args = values(&[("name", "My Cool App")]);
+
+    assert_eq!(renderer.render("{{ name }}|{{name}}|{{  name  }}", &args).unwrap(),
+               "My Cool App|My Cool App|My Cool App");
+    assert_eq!(renderer.render("{{ name | snake_case }}", &args).unwrap(), "my_cool_app");
+    assert_eq!(renderer.render("{{ name | kebab_case }}", &args).unwrap(), "my-cool-app");
+    assert_eq!(renderer.render("{{ name | pascal_case }}", &args).unwrap(), "MyCoolApp");
+    assert_eq!(renderer.render("{{ name | upper }}", &args).unwrap(), "MY COOL APP");
+    assert_eq!(renderer.render("{{ missing | default('none') }}", &args).unwrap(), "none");
+    assert_eq!(renderer.render("plain text\n", &args).unwrap(), "plain text\n");
+}
+
+#[tokio::test]
+async fn test_render_conditionals_and_loops() {
+    let renderer = Renderer::for_args(&[
+        arg("docker", ArgType::Boolean, false, None),
+        arg("port", ArgType::Number, false, None),
+        arg("features", ArgType::Enum, true, Some("|")),
+    ]);
+    let args = values(&[("docker", "false"), ("port", "8080"), ("features", "router|testing")]);
+
+    let source = "{% if docker %}\nFROM node\n{% endif %}\n{% for f in features %}\n- {{ f }}\n{% endfor %}\n";
+    assert_eq!(renderer.render(source, &args).unwrap(), "- router\n- testing\n");
+    assert_eq!(renderer.render("{{ features }}", &args).unwrap(), "router|testing");
+    assert_eq!(renderer.render("{% if 'testing' in features and port > 1024 %}ok{% endif %}", &args).unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn test_render_variables() {
+    let renderer = Renderer::default();
+    let vars = renderer.variables("{{ b }}{% for x in a %}{{ x }}{% endfor %}").unwrap();
+    assert_eq!(vars, vec!["a", "b"]);
+    assert!(renderer.variables("{% if %}").is_err());
+}
+
+#[tokio::test]
+async fn test_render_strict() {
+    let mut renderer = Renderer::default();
+    let args = values(&[("name", "app")]);
+    assert_eq!(renderer.render("{{ name }}-{{ nme }}", &args).unwrap(), "app-");
+
+    renderer.set_strict(true);
+    let err = renderer.render("{{ name }}-{{ nme }}-{{ other }}", &args).unwrap_err();
+    assert_eq!(err.to_string(), "unresolved variables: nme, other");
+    let err = renderer.render("{{ name }}\n{{ name.first }}", &args).unwrap_err();
+    assert_eq!(err.to_string(), "unresolved value `name.first` on line 2");
+    assert_eq!(renderer.render("{% if db %}{{ db }}{% endif %}{{ x | default('y') }}", &args).unwrap(), "y");
+}
+
+#[tokio::test]
+async fn test_render_escapes() {
+    let mut renderer = Renderer::default();
+    renderer.set_strict(true);
+    let args = values(&[("name", "app")]);
+
+    assert!(renderer.render("${{ secrets.TOKEN }}", &values(&[])).is_err());
+    assert_eq!(renderer.render("$\\{{ secrets.TOKEN }} {{ name }}", &args).unwrap(), "${{ secrets.TOKEN }} app");
+    assert_eq!(renderer.render("{% raw %}{{#each items}}{% endraw %}", &args).unwrap(), "{{#each items}}");
+    assert_eq!(renderer.render("{% raw %}\\{{ x }}{% endraw %} \\{{ x }}", &args).unwrap(), "\\{{ x }} {{ x }}");
+}
+
+#[tokio::test]
+async fn test_render_shell_quotes_values() {
+    let renderer = Renderer::default();
+    let args = values(&[("name", "my app"), ("flags", "--release --quiet"), ("plain", "app")]);
+
+    assert_eq!(renderer.render_shell("cd {{ name }} && ls", &args).unwrap(), "cd 'my app' && ls");
+    assert_eq!(renderer.render_shell("cargo build {{ flags | safe }}", &args).unwrap(),
+               "cargo build --release --quiet");
+    assert_eq!(renderer.render_shell("echo {{ name | snake_case }} {{ plain }}", &args).unwrap(), "echo my_app app");
+    assert_eq!(renderer.render_shell("echo {{ (name ~ '!') | shell_quote }}", &args).unwrap(), "echo 'my app!'");
+    assert_eq!(renderer.render("echo {{ name | shell_quote }}", &args).unwrap(), "echo 'my app'");
+    assert_eq!(renderer.render("echo {{ name }}", &args).unwrap(), "echo my app");
+    assert_eq!(renderer.render_shell("echo \\{{ name }} {{ plain }}", &args).unwrap(), "echo {{ name }} app");
+}
+
+#[tokio::test]
+async fn test_render_shell_escapes_values_inside_template_quotes() {
+    let mut renderer = Renderer::default();
+    let args = values(&[("name", "My App"), ("quip", "it's $(rm -rf ~) \"done\"")]);
+
+    assert_eq!(renderer.render_shell("git commit -m \"Initial commit for {{ name }}\"", &args).unwrap(),
+               "git commit -m \"Initial commit for My App\"");
+    assert_eq!(renderer.render_shell("echo \"{{ quip }}\"", &args).unwrap(),
+               "echo \"it's \\$(rm -rf ~) \\\"done\\\"\"");
+    assert_eq!(renderer.render_shell("echo '{{- quip -}}'", &args).unwrap(),
+               "echo 'it'\\''s $(rm -rf ~) \"done\"'");
+    assert_eq!(renderer.render_shell("echo \"a\" {{ name }} 'b' \\\"{{ name }}", &args).unwrap(),
+               "echo \"a\" 'My App' 'b' \\\"'My App'");
+    assert_eq!(renderer.render_shell("echo \"{% raw %}{{ name }}{% endraw %} {{ name | safe }}\"", &args).unwrap(),
+               "echo \"{{ name }} My App\"");
+    assert_eq!(renderer.render_shell("echo \"{{ missing }}\"", &args).unwrap(), "echo \"\"");
+    renderer.set_strict(true);
+    let err = renderer.render_shell("echo \"{{ missing }}\"", &args).unwrap_err();
+    assert_eq!(err.to_string(), "unresolved variables: missing");
+    let err = renderer.render_shell("echo \"{{ name.first }}\"", &args).unwrap_err();
+    assert_eq!(err.to_string(), "unresolved value on line 1");
+}
@@ -1,4 +1,6 @@
-use scaf::model::step::{TemplateStep, StepType, ModificationType, Conditions, LogicalOperator, Condition, ConditionOperator};
+use scaf::model::context::ExecutionContext;
+use scaf::model::condition::{Condition, ConditionOperator, Conditions, LogicalOperator};
+use scaf::model::step::{TemplateStep, StepType, ModificationType, Occurrence, OnError};
 use std::collections::HashMap;
 use tempfile::tempdir;
 
@@ -10,7 +12,7 @@ async fn test_directory_step() {
     let step = TemplateStep {
         id: "test-dir".to_string(),
         description: "Test Directory Creation".to_string(),
-        path: test_dir.to_str().unwrap().to_string(),
+        path: "test_dir".to_string(),
         step_type: StepType::Directory,
         content: None,
         conditions: None,
@@ -24,10 +26,20 @@ async fn test_directory_step() {
         dev_dependencies: None,
         template_engine: None,
         variables: None,
+        cwd: None,
+        env: None,
+        timeout_secs: None,
+        args: None,
+        after: None,
+        before: None,
+        regex: false,
+        occurrence: Occurrence::First,
+        on_error: OnError::Fail,
     };
 
     let args = HashMap::new();
-    assert!(step.execute(&args).await.is_ok());
+    let ctx = ExecutionContext::new(temp_dir.path());
+    assert!(step.execute(&args, &ctx).await.is_ok());
     assert!(test_dir.exists());
 }
 
@@ -39,7 +51,7 @@ async fn test_file_step() {
     let step = TemplateStep {
         id: "test-file".to_string(),
         description: "Test File Creation".to_string(),
-        path: test_file.to_str().unwrap().to_string(),
+        path: "test.txt".to_string(),
         step_type: StepType::File,
         content: Some("test content".to_string()),
         conditions: None,
@@ -53,10 +65,20 @@ async fn test_file_step() {
         dev_dependencies: None,
         template_engine: None,
         variables: None,
+        cwd: None,
+        env: None,
+        timeout_secs: None,
+        args: None,
+        after: None,
+        before: None,
+        regex: false,
+        occurrence: Occurrence::First,
+        on_error: OnError::Fail,
     };
 
     let args = HashMap::new();
-    assert!(step.execute(&args).await.is_ok());
+    let ctx = ExecutionContext::new(temp_dir.path());
+    assert!(step.execute(&args, &ctx).await.is_ok());
     assert!(test_file.exists());
     assert_eq!(tokio::fs::read_to_string(&test_file).await.unwrap(), "test content");
 }
@@ -70,7 +92,7 @@ async fn test_modify_step() {
     let step = TemplateStep {
         id: "test-modify".to_string(),
         description: "Test File Modification".to_string(),
-        path: test_file.to_str().unwrap().to_string(),
+        path: "modify.txt".to_string(),
         step_type: StepType::Modify,
         content: Some("new content".to_string()),
         conditions: None,
@@ -84,10 +106,20 @@ async fn test_modify_step() {
         dev_dependencies: None,
         template_engine: None,
         variables: None,
+        cwd: None,
+        env: None,
+        timeout_secs: None,
+        args: None,
+        after: None,
+        before: None,
+        regex: false,
+        occurrence: Occurrence::First,
+        on_error: OnError::Fail,
     };
 
     let args = HashMap::new();
-    assert!(step.execute(&args).await.is_ok());
+    let ctx = ExecutionContext::new(temp_dir.path());
+    assert!(step.execute(&args, &ctx).await.is_ok());
     let content = tokio::fs::read_to_string(&test_file).await.unwrap();
     assert!(content.contains("original content"));
     assert!(content.contains("new content"));
@@ -101,7 +133,7 @@ async fn test_conditional_step() {
     let step = TemplateStep {
         id: "test-conditional".to_string(),
         description: "Test Conditional Step".to_string(),
-        path: test_file.to_str().unwrap().to_string(),
+        path: "conditional.txt".to_string(),
         step_type: StepType::File,
         content: Some("conditional content".to_string()),
         conditions: Some(Conditions {
@@ -111,7 +143,7 @@ async fn test_conditional_step() {
                     field: "test_flag".to_string(),
                     operator: ConditionOperator::Equals,
                     value: "true".to_string(),
-                }
+                }.into()
             ],
         }),
         line_number: None,
@@ -124,6 +156,15 @@ async fn test_conditional_step() {
         dev_dependencies: None,
         template_engine: None,
         variables: None,
+        cwd: None,
+        env: None,
+        timeout_secs: None,
+        args: None,
+        after: None,
+        before: None,
+        regex: false,
+        occurrence: Occurrence::First,
+        on_error: OnError::Fail,
     };
 
     // Test with condition not met
@@ -132,7 +173,322 @@ async fn test_conditional_step() {
     assert!(!test_file.exists());
 
     args.insert("test_flag".to_string(), "true".to_string());
-    assert!(step.execute(&args).await.is_ok());
+    let ctx = ExecutionContext::new(temp_dir.path());
+    assert!(step.execute(&args, &ctx).await.is_ok());
     assert!(test_file.exists());
     assert_eq!(tokio::fs::read_to_string(&test_file).await.unwrap(), "conditional content");
 }
+
+#[tokio::test]
+async fn test_step_plan() {
+    let step = TemplateStep {
+        id: "install".to_string(),
+        description: "Install packages".to_string(),
+        path: "./{{ name }}".to_string(),
+        step_type: StepType::Command,
+        content: Some("cd {{ name }} && echo 'done'".to_string()),
+        conditions: Some(Conditions {
+            operator: LogicalOperator::And,
+            conditions: vec![Condition {
+                field: "install".to_string(),
+                operator: ConditionOperator::Equals,
+                value: "true".to_string(),
+            }.into()],
+        }),
+        line_number: None,
+        modification_type: None,
+        source: None,
+        url: None,
+        branch: None,
+        package_manager: None,
+        dependencies: None,
+        dev_dependencies: None,
+        template_engine: None,
+        variables: None,
+        cwd: None,
+        env: None,
+        timeout_secs: None,
+        args: None,
+        after: None,
+        before: None,
+        regex: false,
+        occurrence: Occurrence::First,
+        on_error: OnError::Fail,
+    };
+
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), "my app".to_string());
+    args.insert("install".to_string(), "false".to_string());
+
+    let plan = step.plan(&args, &ExecutionContext::default());
+    assert_eq!(plan.path, ".");
+    assert!(!plan.will_run);
+    assert_eq!(plan.command.unwrap(), r#"sh -c 'cd '\''my app'\'' && echo '\''done'\'''"#);
+}
+
+#[tokio::test]
+async fn test_step_paths_stay_in_dest() {
+    let temp_dir = tempdir().unwrap();
+    let ctx = ExecutionContext::new(temp_dir.path().join("project"));
+
+    assert_eq!(ctx.resolve("./src/../main.rs").unwrap(), temp_dir.path().join("project/main.rs"));
+    assert!(ctx.resolve("../outside.txt").is_err());
+    assert!(ctx.resolve("src/../../outside.txt").is_err());
+    assert!(ctx.resolve("/etc/passwd").is_err());
+
+    #[cfg(unix)]
+    {
+        std::fs::create_dir_all(temp_dir.path().join("project")).unwrap();
+        std::os::unix::fs::symlink(temp_dir.path(), temp_dir.path().join("project/link")).unwrap();
+        assert!(ctx.resolve("link/outside.txt").is_err());
+    }
+}
+
+#[tokio::test]
+async fn test_strict_step_reports_unresolved() {
+    let temp_dir = tempdir().unwrap();
+    let step = TemplateStep {
+        id: "readme".to_string(),
+        description: "Readme".to_string(),
+        path: "README.md".to_string(),
+        step_type: StepType::File,
+        content: Some("# {{ project_nmae }}".to_string()),
+        conditions: None,
+        line_number: None,
+        modification_type: None,
+        source: None,
+        url: None,
+        branch: None,
+        package_manager: None,
+        dependencies: None,
+        dev_dependencies: None,
+        template_engine: None,
+        variables: None,
+        cwd: None,
+        env: None,
+        timeout_secs: None,
+        args: None,
+        after: None,
+        before: None,
+        regex: false,
+        occurrence: Occurrence::First,
+        on_error: OnError::Fail,
+    };
+
+    let ctx = ExecutionContext::new(temp_dir.path()).strict(true);
+    let err = step.execute(&HashMap::new(), &ctx).await.unwrap_err();
+    assert_eq!(err.to_string(), "Step readme: failed to render content: unresolved variables: project_nmae");
+    assert!(!temp_dir.path().join("README.md").exists());
+}
+
+fn command_step(extra: &str) -> TemplateStep {
+    serde_json::from_str(&format!(
+        r#"{{"id": "cmd", "description": "Run", "path": ".", "type": "command", {}}}"#, extra
+    )).unwrap()
+}
+
+#[tokio::test]
+async fn test_command_cwd_env_and_failure_policy() {
+    let temp_dir = tempdir().unwrap();
+    std::fs::create_dir_all(temp_dir.path().join("app")).unwrap();
+    let ctx = ExecutionContext::new(temp_dir.path());
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), "app".to_string());
+
+    let step = command_step(r#""content": "echo \"$GREETING\" > out.txt", "cwd": "{{ name }}",
+        "env": {"GREETING": "hello {{ name }}"}"#);
+    step.execute(&args, &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(temp_dir.path().join("app/out.txt")).unwrap(), "hello app\n");
+    assert_eq!(step.plan(&args, &ctx).command.unwrap(), "GREETING='hello app' sh -c 'echo \"$GREETING\" > out.txt'");
+
+    let failing = command_step(r#""content": "echo broken >&2; exit 3""#);
+    let err = failing.execute(&args, &ctx).await.unwrap_err().to_string();
+    assert!(err.starts_with("Step cmd: `sh -c 'echo broken >&2; exit 3'` failed (exit status: 3)"), "{}", err);
+    assert!(err.ends_with("broken"), "{}", err);
+    let quiet = command_step(r#""content": "echo started; echo 'error: no such target'; exit 2""#);
+    let err = quiet.execute(&args, &ctx).await.unwrap_err().to_string();
+    assert!(err.ends_with("failed (exit status: 2):\nstarted\nerror: no such target"), "{}", err);
+
+    for policy in ["warn", "ignore"] {
+        let tolerated = command_step(&format!(r#""content": "exit 1", "on_error": "{}""#, policy));
+        assert!(tolerated.execute(&args, &ctx).await.is_ok());
+    }
+    assert!(command_step(r#""content": "true", "cwd": "../elsewhere""#).execute(&args, &ctx).await.is_err());
+}
+
+#[tokio::test]
+async fn test_command_timeout() {
+    let temp_dir = tempdir().unwrap();
+    let ctx = ExecutionContext::new(temp_dir.path());
+    let step = command_step(r#""content": "sleep 5", "timeout_secs": 1"#);
+
+    let started = std::time::Instant::now();
+    let err = step.execute(&HashMap::new(), &ctx).await.unwrap_err().to_string();
+    assert_eq!(err, "Step cmd: `sh -c 'sleep 5'` timed out after 1s");
+    assert!(started.elapsed() < std::time::Duration::from_secs(4));
+}
+
+#[tokio::test]
+async fn test_command_values_cannot_inject() {
+    let temp_dir = tempdir().unwrap();
+    let ctx = ExecutionContext::new(temp_dir.path());
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), "x; touch pwned".to_string());
+
+    let step = command_step(r#""content": "echo {{ name }} > out.txt""#);
+    step.execute(&args, &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(temp_dir.path().join("out.txt")).unwrap(), "x; touch pwned\n");
+    assert!(!temp_dir.path().join("pwned").exists());
+
+    let argv = command_step(r#""args": ["sh", "-c", "printf %s \"$0\" > argv.txt", "{{ name }}"]"#);
+    assert_eq!(argv.command_argv(&args, &ctx).unwrap().unwrap()[3], "x; touch pwned");
+    argv.execute(&args, &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(temp_dir.path().join("argv.txt")).unwrap(), "x; touch pwned");
+    assert!(!temp_dir.path().join("pwned").exists());
+
+    let escaped = command_step(r#""content": "echo '\\{{ name }}' > escaped.txt""#);
+    assert_eq!(escaped.plan(&args, &ctx).command.unwrap(), "sh -c 'echo '\\''{{ name }}'\\'' > escaped.txt'");
+    escaped.execute(&args, &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(temp_dir.path().join("escaped.txt")).unwrap(), "{{ name }}\n");
+}
+
+/// A directory holding fake `name` executables that log their arguments to `calls.log`.
+fn stub_bin(dir: &std::path::Path, names: &[&str]) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let bin = dir.join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    for name in names {
+        let path = bin.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\necho \"{} $*\" >> \"{}\"\n", name, dir.join("calls.log").display()))
+            .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_dependencies_step() {
+    let temp_dir = tempdir().unwrap();
+    let bin = tempdir().unwrap();
+    let path = stub_bin(bin.path(), &["npm", "pnpm", "cargo"]);
+    std::fs::create_dir_all(temp_dir.path().join("app")).unwrap();
+    let ctx = ExecutionContext::new(temp_dir.path());
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), "app".to_string());
+    args.insert("package_manager".to_string(), "pnpm".to_string());
+
+    let step: TemplateStep = serde_json::from_str(&format!(r#"{{
+        "id": "deps", "description": "Install", "type": "dependencies", "path": "{{{{ name }}}}",
+        "dependencies": ["react@^18", "@types/react"], "dev_dependencies": ["vitest"],
+        "env": {{"PATH": "{}"}}
+    }}"#, path)).unwrap();
+    assert_eq!(step.plan(&args, &ctx).command.unwrap(), format!(
+        "cd {} && pnpm add 'react@^18' @types/react && pnpm add --save-dev vitest",
+        temp_dir.path().join("app").display()
+    ));
+    step.execute(&args, &ctx).await.unwrap();
+
+    args.remove("package_manager");
+    std::fs::write(temp_dir.path().join("app/Cargo.toml"), "").unwrap();
+    step.execute(&args, &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(bin.path().join("calls.log")).unwrap(), "\
+pnpm add react@^18 @types/react
+pnpm add --save-dev vitest
+cargo add react@^18 @types/react
+cargo add --dev vitest
+");
+
+    std::fs::remove_file(temp_dir.path().join("app/Cargo.toml")).unwrap();
+    let err = step.execute(&args, &ctx).await.unwrap_err().to_string();
+    assert!(err.starts_with("Step deps: no package manager given and none detected"), "{}", err);
+    // An earlier step may still create the manifest, so the plan leaves detection to run time.
+    assert_eq!(step.plan(&args, &ctx).command.unwrap(), format!(
+        "cd {} && <package manager detected at run time> 'react@^18' @types/react \
+         && <package manager detected at run time> (dev) vitest",
+        temp_dir.path().join("app").display()
+    ));
+}
+
+fn modify_step(extra: &str) -> TemplateStep {
+    serde_json::from_str(&format!(
+        r#"{{"id": "mod", "description": "Modify", "path": "main.rs", "type": "modify", {}}}"#, extra
+    )).unwrap()
+}
+
+#[tokio::test]
+async fn test_modify_anchors_are_idempotent() {
+    let temp_dir = tempdir().unwrap();
+    let file = temp_dir.path().join("main.rs");
+    std::fs::write(&file, "use a;\n// scaf:imports\n\nfn one() {}\nfn two() {}\n").unwrap();
+    let ctx = ExecutionContext::new(temp_dir.path());
+    let mut args = HashMap::new();
+    args.insert("module".to_string(), "routes".to_string());
+
+    let import = modify_step(r#""content": "use {{ module }};\n", "after": "// scaf:imports""#);
+    import.execute(&args, &ctx).await.unwrap();
+    import.execute(&args, &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(),
+               "use a;\n// scaf:imports\nuse routes;\n\nfn one() {}\nfn two() {}\n");
+
+    let attribute = modify_step(r##""content": "#[inline]", "before": "^fn \\w+", "regex": true, "occurrence": "all""##);
+    attribute.execute(&args, &ctx).await.unwrap();
+    attribute.execute(&args, &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(),
+               "use a;\n// scaf:imports\nuse routes;\n\n#[inline]\nfn one() {}\n#[inline]\nfn two() {}\n");
+
+    let last = modify_step(r#""content": "// end", "after": "}", "occurrence": "last""#);
+    last.execute(&args, &ctx).await.unwrap();
+    assert!(std::fs::read_to_string(&file).unwrap().ends_with("fn two() {}\n// end\n"));
+
+    let missing = modify_step(r#""content": "x", "after": "// scaf:missing""#);
+    let err = missing.execute(&args, &ctx).await.unwrap_err().to_string();
+    assert_eq!(err, "Step mod: no line matches `// scaf:missing`");
+}
+
+#[tokio::test]
+async fn test_modify_only_skips_whole_lines_at_the_insertion_point() {
+    let temp_dir = tempdir().unwrap();
+    let file = temp_dir.path().join("main.rs");
+    std::fs::write(&file, "// app.use(cors())
+const app = express();
+listen(3000);
+listen(3000);
+").unwrap();
+    let ctx = ExecutionContext::new(temp_dir.path());
+    let args = HashMap::new();
+
+    let cors = modify_step(r#""content": "app.use(cors())", "after": "const app""#);
+    cors.execute(&args, &ctx).await.unwrap();
+    cors.execute(&args, &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(),
+               "// app.use(cors())\nconst app = express();\napp.use(cors())\nlisten(3000);\nlisten(3000);\n");
+
+    let port = modify_step(r#""content": "listen(8080);", "modification_type": "replace", "line_number": 5"#);
+    std::fs::write(&file, "listen(8080);\nconst app = express();\napp.use(cors())\nlisten(3000);\nlisten(3000);\n").unwrap();
+    port.execute(&args, &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(),
+               "listen(8080);\nconst app = express();\napp.use(cors())\nlisten(3000);\nlisten(8080);\n");
+
+    let footer = modify_step(r#""content": "export default app;", "modification_type": "append""#);
+    std::fs::write(&file, "// export default app;\n").unwrap();
+    footer.execute(&args, &ctx).await.unwrap();
+    footer.execute(&args, &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "// export default app;\n\nexport default app;\n");
+}
+
+#[tokio::test]
+async fn test_modify_keeps_trailing_newline() {
+    let temp_dir = tempdir().unwrap();
+    let file = temp_dir.path().join("main.rs");
+    std::fs::write(&file, "a\nc\n").unwrap();
+    let ctx = ExecutionContext::new(temp_dir.path());
+
+    let step = modify_step(r#""content": "b", "modification_type": "insertafter", "line_number": 1"#);
+    step.execute(&HashMap::new(), &ctx).await.unwrap();
+    step.execute(&HashMap::new(), &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "a\nb\nc\n");
+
+    std::fs::write(&file, "a").unwrap();
+    modify_step(r#""content": "b", "modification_type": "append""#).execute(&HashMap::new(), &ctx).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "a\n\nb");
+}
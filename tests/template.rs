@@ -1,8 +1,11 @@
 
 use std::collections::HashMap;
+use scaf::answers::Answers;
 use scaf::args::{ArgType, TemplateArg};
+use scaf::context::ExecutionContext;
 use scaf::language::Language;
-use scaf::step::{Condition, ConditionOperator, Conditions, LogicalOperator, StepType, TemplateStep};
+use scaf::condition::{Condition, ConditionOperator, Conditions, LogicalOperator};
+use scaf::step::{Occurrence, OnError, StepType, TemplateStep};
 use scaf::Template;
 
 fn create_test_template() -> Template {
@@ -14,9 +17,12 @@ fn create_test_template() -> Template {
         author: "Test Author".to_string(),
         language: Language::Rust,
         tags: vec!["test".to_string(), "template".to_string()],
+        min_scaf_version: None,
+        groups: vec![],
         args: vec![],
         extends: vec![],
         steps: vec![],
+        files: vec![],
     }
 }
 
@@ -29,30 +35,66 @@ async fn test_template_basic_info() {
     assert_eq!(template.author, "Test Author");
     assert_eq!(template.tags, vec!["test", "template"]);
 }
-//
-// #[tokio::test]
-// async fn test_template_with_args() {
-//     let mut template = create_test_template();
-//     template.args = vec![TemplateArg {
-//         name: "Project Name".to_string(),
-//         key: "project_name".to_string(),
-//         description: Some("Test project name".to_string()),
-//         long_description: None,
-//         arg_type: ArgType::String,
-//         default: "test-project".to_string(),
-//         values: None,
-//         required: true,
-//         pattern: None,
-//         group: None,
-//         depends_on: None,
-//         multiple: false,
-//
-//         delimiter: None,
-//     }];
-//
-//     let args_result = template.collect_arguments().await;
-//     assert!(args_result.is_err());
-// }
+
+#[tokio::test]
+async fn test_template_with_args() {
+    let mut template = create_test_template();
+    template.args = vec![TemplateArg {
+        name: "Project Name".to_string(),
+        key: "project_name".to_string(),
+        description: Some("Test project name".to_string()),
+        long_description: None,
+        arg_type: ArgType::String,
+        default: "test-project".to_string(),
+        values: None,
+        required: true,
+        pattern: None,
+        group: None,
+        depends_on: None,
+        multiple: false,
+        delimiter: None,
+        secret: false,
+        conditions: None,
+    }];
+
+    let answers = Answers::new(true);
+    let args = template.collect_arguments(&answers).await.unwrap();
+    assert_eq!(args.get("project_name").unwrap(), "test-project");
+
+    let mut answers = Answers::new(true);
+    answers.insert_pair("project_name=from-flag").unwrap();
+    let args = template.collect_arguments(&answers).await.unwrap();
+    assert_eq!(args.get("project_name").unwrap(), "from-flag");
+}
+
+#[tokio::test]
+async fn test_template_missing_required_arg() {
+    let mut template = create_test_template();
+    template.args = vec![TemplateArg {
+        name: "Owner".to_string(),
+        key: "owner".to_string(),
+        description: None,
+        long_description: None,
+        arg_type: ArgType::Email,
+        default: "".to_string(),
+        values: None,
+        required: true,
+        pattern: None,
+        group: None,
+        depends_on: None,
+        multiple: false,
+        delimiter: None,
+        secret: false,
+        conditions: None,
+    }];
+
+    let err = template.collect_arguments(&Answers::new(true)).await.unwrap_err();
+    assert!(err.to_string().contains("Missing required argument `owner`"));
+
+    let mut answers = Answers::new(true);
+    answers.insert_pair("owner=not-an-email").unwrap();
+    assert!(template.collect_arguments(&answers).await.is_err());
+}
 
 #[tokio::test]
 async fn test_template_with_steps() {
@@ -74,14 +116,23 @@ async fn test_template_with_steps() {
         dev_dependencies: None,
         template_engine: None,
         variables: None,
+        cwd: None,
+        env: None,
+        timeout_secs: None,
+        args: None,
+        after: None,
+        before: None,
+        regex: false,
+        occurrence: Occurrence::First,
+        on_error: OnError::Fail,
     }];
 
     let args = HashMap::new();
-    let result = template.execute(&args).await;
+    let result = template.execute(&args, &ExecutionContext::default()).await;
     assert!(result.is_ok());
-    let (executed, skipped) = result.unwrap();
-    assert_eq!(executed, 1);
-    assert_eq!(skipped, 0);
+    let report = result.unwrap();
+    assert_eq!(report.executed.len(), 1);
+    assert_eq!(report.skipped.len(), 0);
 }
 
 #[tokio::test]
@@ -102,7 +153,7 @@ async fn test_template_with_conditions() {
                 field: "test_condition".to_string(),
                 operator: ConditionOperator::Equals,
                 value: "true".to_string(),
-            }],
+            }.into()],
         }),
         line_number: None,
         modification_type: None,
@@ -114,11 +165,122 @@ async fn test_template_with_conditions() {
         dev_dependencies: None,
         template_engine: None,
         variables: None,
+        cwd: None,
+        env: None,
+        timeout_secs: None,
+        args: None,
+        after: None,
+        before: None,
+        regex: false,
+        occurrence: Occurrence::First,
+        on_error: OnError::Fail,
     }];
 
-    let result = template.execute(&conditions).await;
+    let result = template.execute(&conditions, &ExecutionContext::default()).await;
     assert!(result.is_ok());
-    let (executed, skipped) = result.unwrap();
-    assert_eq!(executed, 1);
-    assert_eq!(skipped, 0);
+    let report = result.unwrap();
+    assert_eq!(report.executed.len(), 1);
+    assert_eq!(report.skipped.len(), 0);
+}
+
+#[tokio::test]
+async fn test_arg_groups_keep_declared_order_and_conditions() {
+    let template: Template = serde_json::from_str(r#"{
+        "name": "Grouped", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [], "steps": [],
+        "groups": [
+            {"id": "basics", "title": "Basic Configuration", "description": "The essentials"},
+            {"id": "database", "title": "Database",
+             "conditions": {"conditions": [{"field": "use_db", "operator": "equals", "value": "true"}]}},
+            {"id": "cache", "title": "Cache",
+             "conditions": {"conditions": [{"field": "plain", "operator": "equals", "value": "p"}]}}
+        ],
+        "args": [
+            {"name": "Cache ttl", "key": "cache_ttl", "type": "number", "default": "60", "group": "cache"},
+            {"name": "Extra", "key": "extra", "type": "string", "default": "x", "group": "extras"},
+            {"name": "Db host", "key": "db_host", "type": "string", "default": "", "group": "database"},
+            {"name": "Plain", "key": "plain", "type": "string", "default": "p"},
+            {"name": "Use db", "key": "use_db", "type": "boolean", "default": "false", "group": "basics"}
+        ]
+    }"#).unwrap();
+
+    let sections: Vec<(&str, Vec<&str>)> = template.sections()
+        .iter()
+        .map(|s| (s.title, s.args.iter().map(|a| a.key.as_str()).collect()))
+        .collect();
+    assert_eq!(sections, vec![
+        ("Basic Configuration", vec!["use_db"]),
+        ("Database", vec!["db_host"]),
+        ("extras", vec!["extra"]),
+        ("General", vec!["plain"]),
+        ("Cache", vec!["cache_ttl"]),
+    ]);
+    assert!(template.validate().is_empty());
+
+    let args = template.collect_arguments(&Answers::new(true)).await.unwrap();
+    assert_eq!(args.get("use_db").map(String::as_str), Some("false"));
+    assert!(!args.contains_key("db_host"));
+    assert_eq!(args.get("cache_ttl").map(String::as_str), Some("60"));
+    assert_eq!(args.len(), 4);
+
+    let mut answers = Answers::new(true);
+    answers.insert_pair("use_db=true").unwrap();
+    let args = template.collect_arguments(&answers).await.unwrap();
+    assert_eq!(args.get("db_host").map(String::as_str), Some(""));
+}
+
+#[tokio::test]
+async fn test_conditional_args_follow_their_dependencies() {
+    let template: Template = serde_json::from_str(r#"{
+        "name": "Conditional", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [], "steps": [],
+        "args": [
+            {"name": "Database URL", "key": "db_url", "type": "string", "default": "",
+             "conditions": {"conditions": [{"field": "database", "operator": "notequals", "value": "none"}]}},
+            {"name": "Pool size", "key": "pool", "type": "number", "default": "5", "depends_on": ["db_url"],
+             "conditions": {"conditions": [{"field": "database", "operator": "equals", "value": "postgres"}]}},
+            {"name": "Database", "key": "database", "type": "string", "default": "none"}
+        ]
+    }"#).unwrap();
+
+    let order: Vec<&str> = template.ordered_args().iter().map(|a| a.key.as_str()).collect();
+    assert_eq!(order, vec!["database", "db_url", "pool"]);
+
+    let args = template.collect_arguments(&Answers::new(true)).await.unwrap();
+    assert_eq!(args.get("database").map(String::as_str), Some("none"));
+    assert!(!args.contains_key("db_url"));
+    assert_eq!(args.get("pool").map(String::as_str), Some("5"));
+
+    let mut answers = Answers::new(true);
+    answers.insert_pair("database=postgres").unwrap();
+    answers.insert_pair("db_url=postgres://localhost/app").unwrap();
+    answers.insert_pair("pool=20").unwrap();
+    let args = template.collect_arguments(&answers).await.unwrap();
+    assert_eq!(args.get("db_url").map(String::as_str), Some("postgres://localhost/app"));
+    assert_eq!(args.get("pool").map(String::as_str), Some("20"));
+}
+
+#[tokio::test]
+async fn test_groups_are_asked_after_the_groups_they_depend_on() {
+    let template: Template = serde_json::from_str(r#"{
+        "name": "Cross group", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [], "steps": [],
+        "groups": [{"id": "db", "title": "Database"}],
+        "args": [
+            {"name": "Database URL", "key": "db_url", "type": "string", "default": "", "group": "db",
+             "conditions": {"conditions": [{"field": "database", "operator": "notequals", "value": "none"}]}},
+            {"name": "Database", "key": "database", "type": "string", "default": "none"}
+        ]
+    }"#).unwrap();
+
+    let titles: Vec<&str> = template.sections().iter().map(|s| s.title).collect();
+    assert_eq!(titles, vec!["General", "Database"]);
+    assert!(template.validate().is_empty());
+
+    let mut answers = Answers::new(true);
+    answers.insert_pair("database=postgres").unwrap();
+    answers.insert_pair("db_url=pg://x").unwrap();
+    let args = template.collect_arguments(&answers).await.unwrap();
+    assert_eq!(args.get("database").map(String::as_str), Some("postgres"));
+    assert_eq!(args.get("db_url").map(String::as_str), Some("pg://x"));
 }
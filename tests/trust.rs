@@ -0,0 +1,115 @@
+mod common;
+
+use std::collections::HashMap;
+use scaf::config::Config;
+use scaf::context::ExecutionContext;
+use scaf::loader::{load_template_with_sources, TemplateSource};
+use scaf::model::step::StepType;
+use scaf::model::Template;
+use scaf::trust::{authorize, is_trusted, side_effects, SideEffectPolicy};
+use tempfile::tempdir;
+use common::{cached_in, serve_body};
+
+fn template() -> Template {
+    serde_json::from_str(r#"{
+        "name": "Effects", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [],
+        "args": [{"name": "Name", "key": "name", "type": "string", "default": "app"}],
+        "steps": [
+            {"id": "readme", "description": "Readme", "type": "file", "path": "README.md", "content": "hi"},
+            {"id": "marker", "description": "Marker", "type": "command", "path": ".",
+             "content": "touch ran-{{ name }}"},
+            {"id": "logo", "description": "Logo", "type": "download", "path": "logo.png",
+             "url": "https://example.com/{{ name }}.png",
+             "conditions": "name == 'never'"}
+        ]
+    }"#).unwrap()
+}
+
+fn trusting(origins: &[&str]) -> Config {
+    Config {
+        trusted: origins.iter().map(|origin| origin.to_string()).collect(),
+        ..Config::default()
+    }
+}
+
+#[tokio::test]
+async fn test_trusted_origins() {
+    let config = trusting(&["https://github.com/acme", "https://registry.example.com/"]);
+    let parse = |reference: &str| TemplateSource::parse(reference, false, &config).unwrap();
+
+    assert!(is_trusted(&TemplateSource::File("template.json".into()), &config));
+    assert!(is_trusted(&parse("gh:acme/templates@v1"), &config));
+    assert!(is_trusted(&parse("https://github.com/acme/templates/raw/main/t.json"), &config));
+    assert!(!is_trusted(&parse("https://github.com/acme-evil/t.json"), &config));
+    assert!(!is_trusted(&parse("gh:other/templates"), &config));
+
+    let registry = Config { registry_url: Some("https://registry.example.com".to_string()), ..config.clone() };
+    let parse = |reference: &str| TemplateSource::parse(reference, false, &registry).unwrap();
+    assert!(is_trusted(&parse("acme/service@1.2.0"), &registry));
+    assert!(!is_trusted(&parse("acme/service@^1.2"), &registry));
+    assert!(!is_trusted(&parse("acme/service"), &registry));
+    assert!(!is_trusted(&parse("acme/service@1.2.0"), &Config::default()));
+}
+
+#[tokio::test]
+async fn test_side_effects_policy() {
+    let temp_dir = tempdir().unwrap();
+    let template = template();
+    let ctx = ExecutionContext::new(temp_dir.path()).with_template(&template);
+    let args = HashMap::from([("name".to_string(), "app".to_string())]);
+
+    let effects = side_effects(&template, &args, &ctx);
+    assert_eq!(effects.len(), 1);
+    assert_eq!(effects[0].step, "marker");
+    assert_eq!(effects[0].step_type, StepType::Command);
+    assert_eq!(effects[0].detail, "sh -c 'touch ran-app'");
+
+    let remote = TemplateSource::Url("https://example.com/t.json".parse().unwrap());
+    let config = Config::default();
+    let err = authorize(&effects, std::slice::from_ref(&remote), &config, SideEffectPolicy::Ask, true).unwrap_err();
+    assert!(err.to_string().contains("--allow-commands"), "{}", err);
+    assert!(authorize(&effects, std::slice::from_ref(&remote), &config, SideEffectPolicy::Allow, true).unwrap());
+    assert!(authorize(&effects, std::slice::from_ref(&remote), &trusting(&["https://example.com"]), SideEffectPolicy::Ask, true).unwrap());
+    assert!(authorize(&[], std::slice::from_ref(&remote), &config, SideEffectPolicy::Ask, true).unwrap());
+
+    let allowed = authorize(&effects, std::slice::from_ref(&remote), &config, SideEffectPolicy::Skip, true).unwrap();
+    assert!(!allowed);
+    let report = template.execute(&args, &ctx.clone().side_effects(allowed)).await.unwrap();
+    assert_eq!(report.executed, vec!["readme"]);
+    assert_eq!(report.skipped, vec!["marker", "logo"]);
+    assert!(temp_dir.path().join("README.md").exists());
+    assert!(!temp_dir.path().join("ran-app").exists());
+
+    template.execute(&args, &ctx).await.unwrap();
+    assert!(temp_dir.path().join("ran-app").exists());
+}
+
+#[tokio::test]
+async fn test_trusted_child_extending_untrusted_parent() {
+    let base = serve_body(r#"{
+        "name": "Parent", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [], "args": [],
+        "steps": [{"id": "evil", "description": "", "type": "command", "path": ".", "content": "echo parent"}]
+    }"#);
+    let dir = tempdir().unwrap();
+    let child = dir.path().join("template.json");
+    std::fs::write(&child, format!(r#"{{
+        "name": "Child", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "args": [], "steps": [],
+        "extends": [{{"template_id": "{}/parent.json", "version": "*", "args": []}}]
+    }}"#, base)).unwrap();
+
+    let cache = tempdir().unwrap();
+    let config = cached_in(cache.path());
+    let (template, sources) = load_template_with_sources(&TemplateSource::File(child), &config).await.unwrap();
+    assert_eq!(sources.len(), 2);
+    assert!(is_trusted(&sources[0], &config));
+
+    let ctx = ExecutionContext::new(dir.path()).with_template(&template);
+    let effects = side_effects(&template, &HashMap::new(), &ctx);
+    assert_eq!(effects[0].step, "evil");
+    let err = authorize(&effects, &sources, &config, SideEffectPolicy::Ask, true).unwrap_err().to_string();
+    assert!(err.starts_with(&format!("Template {}/parent.json is not trusted", base)), "{}", err);
+    assert!(authorize(&effects, &sources, &Config { trusted: vec![base], ..config }, SideEffectPolicy::Ask, true).unwrap());
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::path::Path;
+use scaf::context::ExecutionContext;
+use scaf::manifest::ProjectManifest;
+use scaf::update::update;
+use scaf::Template;
+use tempfile::tempdir;
+
+fn template(version: &str, readme: &str, config: &str, extra_steps: &str) -> Template {
+    let json = format!(r#"{{
+        "name": "Service", "version": "{}", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [],
+        "args": [{{"name": "Name", "key": "name", "type": "string", "default": "demo"}}],
+        "steps": [
+            {{"id": "readme", "description": "", "type": "file", "path": "README.md", "content": {}}},
+            {{"id": "config", "description": "", "type": "file", "path": "config.toml", "content": {}}},
+            {{"id": "log", "description": "", "type": "command", "path": ".", "content": "echo run >> log.txt"}}
+            {}
+        ]
+    }}"#, version, serde_json::to_string(readme).unwrap(), serde_json::to_string(config).unwrap(), extra_steps);
+    serde_json::from_str(&json).unwrap()
+}
+
+fn read(dir: &Path, file: &str) -> String {
+    std::fs::read_to_string(dir.join(file)).unwrap()
+}
+
+#[tokio::test]
+async fn test_update_merges_three_ways() {
+    let dest = tempdir().unwrap();
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), "demo".to_string());
+
+    let v1 = template("1.0.0", "# {{ name }}\nintro\n\nfooter\n", "port = 80\n", "");
+    let ctx = ExecutionContext::new(dest.path()).with_template(&v1);
+    let report = v1.execute(&args, &ctx).await.unwrap();
+    ProjectManifest::new(&v1, "service.json", &args, &report, &ctx).unwrap()
+        .save(dest.path(), dest.path())
+        .unwrap();
+
+    std::fs::write(dest.path().join("README.md"), "# demo\nintro, edited\n\nfooter\n").unwrap();
+    std::fs::write(dest.path().join("config.toml"), "port = 8080\n").unwrap();
+
+    let v2 = template("2.0.0", "# {{ name }}\nintro\n\nnew footer\n", "port = 443\n",
+        r#", {"id": "license", "description": "", "type": "file", "path": "LICENSE", "content": "MIT"},
+           {"id": "hello", "description": "", "type": "command", "path": ".", "content": "echo hi > hello.txt"}"#);
+    let previous = ProjectManifest::load(dest.path()).unwrap();
+    let ctx = ExecutionContext::new(dest.path()).with_template(&v2);
+    let report = update(&v2, "service.json", &previous, &args, &ctx).await.unwrap();
+
+    assert_eq!(read(dest.path(), "README.md"), "# demo\nintro, edited\n\nnew footer\n");
+    assert_eq!(report.merged, vec!["README.md"]);
+    assert_eq!(report.conflicts, vec!["config.toml"]);
+    let config = read(dest.path(), "config.toml");
+    assert!(config.contains("<<<<<<<") && config.contains("port = 8080") && config.contains("port = 443"));
+    assert_eq!(report.added, vec!["LICENSE"]);
+    assert_eq!(report.steps, vec!["hello"]);
+    assert_eq!(read(dest.path(), "log.txt"), "run\n");
+    assert_eq!(read(dest.path(), "hello.txt"), "hi\n");
+
+    let manifest = ProjectManifest::load(dest.path()).unwrap();
+    assert_eq!(manifest.version, "2.0.0");
+    assert_eq!(manifest.executed_steps, vec!["readme", "config", "log", "license", "hello"]);
+    assert_eq!(read(&dest.path().join(".scaf/base"), "config.toml"), "port = 443\n");
+}
+
+#[tokio::test]
+async fn test_update_replaces_untouched_files_and_removes_dropped_ones() {
+    let dest = tempdir().unwrap();
+    let args = HashMap::new();
+
+    let v1 = template("1.0.0", "one\n", "old\n", "");
+    let ctx = ExecutionContext::new(dest.path()).with_template(&v1);
+    let report = v1.execute(&args, &ctx).await.unwrap();
+    ProjectManifest::new(&v1, "service.json", &args, &report, &ctx).unwrap()
+        .save(dest.path(), dest.path())
+        .unwrap();
+
+    let mut v2 = template("1.1.0", "two\n", "", "");
+    v2.steps.retain(|step| step.id != "config");
+    let previous = ProjectManifest::load(dest.path()).unwrap();
+    let ctx = ExecutionContext::new(dest.path()).with_template(&v2);
+    let report = update(&v2, "service.json", &previous, &args, &ctx).await.unwrap();
+
+    assert_eq!(report.updated, vec!["README.md"]);
+    assert_eq!(report.removed, vec!["config.toml"]);
+    assert_eq!(read(dest.path(), "README.md"), "two\n");
+    assert!(!dest.path().join("config.toml").exists());
+    assert!(report.steps.is_empty());
+}
+
+#[tokio::test]
+async fn test_update_keeps_files_without_a_base_copy() {
+    let dest = tempdir().unwrap();
+    let args = HashMap::new();
+
+    let v1 = template("1.0.0", "one\n", "old\n", "");
+    let ctx = ExecutionContext::new(dest.path()).with_template(&v1);
+    let report = v1.execute(&args, &ctx).await.unwrap();
+    ProjectManifest::new(&v1, "service.json", &args, &report, &ctx).unwrap()
+        .save(dest.path(), dest.path())
+        .unwrap();
+
+    std::fs::write(dest.path().join("LICENSE"), "Apache-2.0\n").unwrap();
+    let v2 = template("1.1.0", "one\n", "old\n",
+        r#", {"id": "license", "description": "", "type": "file", "path": "LICENSE", "content": "MIT\n"}"#);
+    let previous = ProjectManifest::load(dest.path()).unwrap();
+    let ctx = ExecutionContext::new(dest.path()).with_template(&v2);
+    let report = update(&v2, "service.json", &previous, &args, &ctx).await.unwrap();
+
+    assert_eq!(report.conflicts, vec!["LICENSE"]);
+    assert_eq!(read(dest.path(), "LICENSE"), "Apache-2.0\n");
+    assert_eq!(read(dest.path(), "LICENSE.scaf-new"), "MIT\n");
+}
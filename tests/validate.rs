@@ -0,0 +1,95 @@
+use scaf::Template;
+
+fn parse(json: &str) -> Template {
+    serde_json::from_str(json).unwrap()
+}
+
+#[tokio::test]
+async fn test_valid_template() {
+    let template = parse(include_str!("../samples/react-app-template.json"));
+    assert!(template.validate().is_empty());
+}
+
+#[tokio::test]
+async fn test_step_problems() {
+    let template = parse(r#"{
+        "name": "Broken", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [],
+        "args": [{"name": "Name", "key": "name", "type": "string", "default": "app"}],
+        "steps": [
+            {"id": "a", "description": "", "type": "file", "path": "{{ name }}/{{ nme }}", "content": "{{ name }}"},
+            {"id": "a", "description": "", "type": "directory", "path": "x",
+             "conditions": {"conditions": [{"field": "missing", "operator": "equals", "value": "1"}]}},
+            {"id": "b", "description": "", "type": "command", "path": ".", "content": "ls", "args": ["{{ nme }}"]},
+            {"id": "c", "description": "", "type": "dependencies", "path": ".", "package_manager": "gradle"},
+            {"id": "d", "description": "", "type": "modify", "path": "x", "content": "",
+             "after": "^mod", "regex": true, "line_number": 2}
+        ]
+    }"#);
+
+    let messages: Vec<String> = template.validate().iter().map(|i| i.to_string()).collect();
+    assert_eq!(messages, vec![
+        "step a: path uses unknown placeholder `{{ nme }}`",
+        "step a: duplicate step id",
+        "step a: condition refers to unknown argument `missing`",
+        "step b: command step has both content and args",
+        "step b: args[0] uses unknown placeholder `{{ nme }}`",
+        "step c: dependencies step lists no packages",
+        "step c: unknown package manager `gradle` (expected one of npm, yarn, pnpm, bun, cargo, pip, uv, poetry, go)",
+        "step d: modify step combines an anchor with modification_type or line_number",
+    ]);
+}
+
+#[tokio::test]
+async fn test_arg_problems() {
+    let template = parse(r#"{
+        "name": "Broken", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [], "steps": [],
+        "args": [
+            {"name": "A", "key": "a", "type": "enum", "default": "x,z", "multiple": true,
+             "values": [{"value": "x", "description": "X"}], "depends_on": ["b"]},
+            {"name": "B", "key": "b", "type": "string", "default": "", "pattern": "(", "depends_on": ["a"]}
+        ]
+    }"#);
+
+    let issues = template.validate();
+    assert_eq!(issues.len(), 3);
+    assert_eq!(issues[0].to_string(), "arg a: default `z` is not one of the allowed values");
+    assert!(issues[1].message.starts_with("invalid pattern `(`"));
+    assert_eq!(issues[2].to_string(), "arg a: dependency cycle: a -> b -> a");
+}
+
+#[tokio::test]
+async fn test_group_problems() {
+    let template = parse(r#"{
+        "name": "Broken", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [], "steps": [], "args": [],
+        "groups": [
+            {"id": "db", "title": "Database"},
+            {"id": "db", "title": "Storage",
+             "conditions": {"conditions": [{"field": "use_db", "operator": "equals", "value": "true"}]}}
+        ]
+    }"#);
+
+    let messages: Vec<String> = template.validate().iter().map(|i| i.to_string()).collect();
+    assert_eq!(messages, vec![
+        "group db: duplicate group id",
+        "group db: condition refers to unknown argument `use_db`",
+    ]);
+}
+
+#[tokio::test]
+async fn test_group_condition_on_its_own_arg() {
+    let template = parse(r#"{
+        "name": "Broken", "version": "1.0.0", "description": "", "author": "", "language": "rust",
+        "tags": [], "extends": [], "steps": [],
+        "groups": [
+            {"id": "db", "title": "Database",
+             "conditions": {"conditions": [{"field": "use_db", "operator": "equals", "value": "true"}]}}
+        ],
+        "args": [{"name": "Use db", "key": "use_db", "type": "boolean", "default": "false", "group": "db"}]
+    }"#);
+
+    let messages: Vec<String> = template.validate().iter().map(|i| i.to_string()).collect();
+    assert_eq!(messages, vec!["group db: condition refers to `use_db`, which is not asked before the group"]);
+}
@@ -0,0 +1,37 @@
+use scaf::version::{check_min_scaf_version, highest_match, is_requirement, parse_requirement, parse_version};
+
+#[test]
+fn test_bare_versions_pin_exactly() {
+    let exact = parse_requirement("1.2.0").unwrap();
+    assert!(exact.matches(&parse_version("1.2.0").unwrap()));
+    assert!(!exact.matches(&parse_version("1.2.1").unwrap()));
+
+    let caret = parse_requirement("^1.2").unwrap();
+    assert!(caret.matches(&parse_version("1.9.0").unwrap()));
+    assert!(!caret.matches(&parse_version("2.0.0").unwrap()));
+
+    assert!(parse_requirement("latest").unwrap().matches(&parse_version("0.1.0").unwrap()));
+    assert!(parse_requirement("one point two").is_err());
+    assert!(parse_version("1.2").is_err());
+}
+
+#[test]
+fn test_highest_match_ignores_non_versions() {
+    let tags = ["v1.0.0", "v1.4.2", "1.10.0", "v2.0.0", "nightly", "v1.5.0-beta.1"];
+    let wanted = parse_requirement("^1.2").unwrap();
+    assert_eq!(highest_match(&wanted, tags), Some("1.10.0"));
+    assert_eq!(highest_match(&parse_requirement("~1.4").unwrap(), tags), Some("v1.4.2"));
+    assert_eq!(highest_match(&parse_requirement("^3").unwrap(), tags), None);
+
+    assert!(is_requirement("^1.2"));
+    assert!(is_requirement(">=1, <2"));
+    assert!(!is_requirement("v1.2"));
+    assert!(!is_requirement("main"));
+}
+
+#[test]
+fn test_min_scaf_version() {
+    assert!(check_min_scaf_version("T", "0.0.0").is_ok());
+    let err = check_min_scaf_version("T", "99.0.0").unwrap_err().to_string();
+    assert!(err.contains("requires scaf 99.0.0 or newer"), "{}", err);
+}